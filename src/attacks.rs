@@ -0,0 +1,100 @@
+use std::collections::{HashMap, HashSet};
+
+use glam::IVec2 as Vec2;
+
+use crate::config::Topology;
+use crate::pieces::{PieceColor, PieceType};
+use crate::state::State;
+
+// every square one side attacks, built once so a position can be queried many times.
+// rays are only followed as far as the longest leap past the outermost piece, the ones still going
+// are kept so squares further out on an infinite board can be answered too.
+// it is opt in: is_square_attacked, checkers and make_move never build or update one, and a map
+// describes only the position it was built from. control_map is its one user in the crate
+#[derive(Debug, Clone)]
+pub struct AttackMap {
+    attackers: HashMap<Vec2, Vec<Vec2>>,
+    escaping: Vec<(Vec2, Vec2)>,
+    low: Vec2,
+    high: Vec2,
+}
+
+impl AttackMap {
+    pub fn new(state: &State, color: PieceColor) -> AttackMap {
+        let pieces = state.get_pieces();
+        let alive: Vec<_> = pieces.iter().filter(|piece| piece.is_alive()).collect();
+        let occupied: HashSet<Vec2> = alive.iter().map(|piece| *piece.get_position()).collect();
+        
+        let (low, high) = match state.config.topology {
+            Topology::Toroidal { width, height } => (Vec2::ONE, Vec2::new(width, height)),
+            _ => {
+                let low = occupied.iter().fold(Vec2::MAX, |low, square| low.min(*square));
+                let high = occupied.iter().fold(Vec2::MIN, |high, square| high.max(*square));
+                // every leap lands inside, so only rides can reach past it
+                let margin = Vec2::splat(state.config.pieces.longest_leap());
+                (low.saturating_sub(margin), high.saturating_add(margin))
+            },
+        };
+        let mut map = AttackMap { attackers: HashMap::new(), escaping: vec![], low, high };
+        
+        for piece in alive.iter().filter(|piece| piece.get_color() == color) {
+            let origin = *piece.get_position();
+            let piece_type = piece.get_piece_type();
+            let mut steps = state.config.pieces.leaper_offsets(piece_type, color);
+            let rays = state.config.pieces.rider_offsets(piece_type, color);
+            // a pawn only attacks with its captures
+            if piece_type == PieceType::PAWN {
+                steps.remove(0);
+            }
+            for step in steps {
+                let square = state.wrap(origin.saturating_add(step));
+                if state.is_in_bounds(square) && (square != origin) {
+                    map.add(square, origin);
+                }
+            }
+            for direction in rays {
+                let mut square = state.wrap(origin.saturating_add(direction));
+                while state.is_in_bounds(square) && (square != origin) {
+                    if !map.is_inside(square) {
+                        map.escaping.push((origin, direction));
+                        break;
+                    }
+                    map.add(square, origin);
+                    if occupied.contains(&square) {
+                        break;
+                    }
+                    square = state.wrap(square.saturating_add(direction));
+                }
+            }
+        }
+        map
+    }
+    
+    fn add(&mut self, square: Vec2, attacker: Vec2) {
+        self.attackers.entry(square).or_default().push(attacker);
+    }
+    
+    fn is_inside(&self, square: Vec2) -> bool {
+        square.cmpge(self.low).all() && square.cmple(self.high).all()
+    }
+    
+    // squares of the pieces attacking square
+    pub fn attackers(&self, square: Vec2) -> Vec<Vec2> {
+        if self.is_inside(square) {
+            return self.attackers.get(&square).cloned().unwrap_or_default();
+        }
+        // nothing stands out here, so any ray that left the box along this line reaches it
+        self.escaping.iter()
+            .filter(|(origin, direction)| {
+                let relative = square.as_i64vec2() - origin.as_i64vec2();
+                let distance = relative.abs().max_element() / direction.abs().max_element() as i64;
+                direction.as_i64vec2() * distance == relative
+            })
+            .map(|(origin, _)| *origin)
+            .collect()
+    }
+    
+    pub fn is_attacked(&self, square: Vec2) -> bool {
+        !self.attackers(square).is_empty()
+    }
+}
@@ -1,19 +1,58 @@
+use std::sync::Arc;
+
 use glam::IVec2 as Vec2;
 
+use crate::pieces::{CustomPiece, PieceRegistry, PieceType};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Topology {
+    Infinite,
+    Bounded,
+    // squares wrap around so files 1..=width and ranks 1..=height form a closed surface
+    Toroidal { width: i32, height: i32 },
+}
+
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Config {
     pub boundaries: [Vec2;2],
     pub promotion_lines: Vec<i32>,
+    pub promotion_options: Vec<PieceType>,
+    pub topology: Topology,
+    // shared by every state of the game, registering copies it only if another state still holds it
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub pieces: Arc<PieceRegistry>,
+}
+
+fn standard_promotions() -> Vec<PieceType> {
+    vec![PieceType::BISHOP, PieceType::KNIGHT, PieceType::ROOK, PieceType::QUEEN]
 }
 
 impl Config {
     pub fn new(boundaries: [Vec2;2] , promotion_lines: Vec<i32>) -> Config {
-        Config { boundaries, promotion_lines }
+        Config { boundaries, promotion_lines, promotion_options: standard_promotions(), topology: Topology::Bounded, pieces: Arc::default() }
+    }
+    
+    pub fn toroidal(width: i32, height: i32, promotion_lines: Vec<i32>) -> Config {
+        let boundaries = [Vec2::new(0, height + 1), Vec2::new(width + 1, 0)];
+        Config { boundaries, promotion_lines, promotion_options: standard_promotions(), topology: Topology::Toroidal { width, height }, pieces: Arc::default() }
+    }
+    
+    // one of the options, and never a king, a pawn or nothing even if it was put among them
+    pub fn allows_promotion(&self, piece_type: PieceType) -> bool {
+        !matches!(piece_type, PieceType::NULL | PieceType::PAWN | PieceType::KING) && self.promotion_options.contains(&piece_type)
     }
     
-    pub fn default() -> Config {
+    pub fn register_piece(&mut self, piece: CustomPiece) -> Result<PieceType, String> {
+        Arc::make_mut(&mut self.pieces).register(piece)
+    }
+}
+
+impl Default for Config {
+    fn default() -> Config {
         let boundaries = [Vec2::new(i32::MIN, i32::MAX), Vec2::new(i32::MAX, i32::MIN)];
         let promotion_lines = vec![1,8];
-        Config { boundaries, promotion_lines }
+        Config { boundaries, promotion_lines, promotion_options: standard_promotions(), topology: Topology::Infinite, pieces: Arc::default() }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,78 @@
+use glam::IVec2 as Vec2;
+
+use crate::moves::Generator;
+use crate::pieces::{PieceColor, PieceType};
+use crate::state::State;
+
+pub const MATE: i32 = 1_000_000;
+
+// centipawns per pseudo legal move
+pub const MOBILITY_WEIGHT: i32 = 4;
+// sliders on an infinite board never run out of moves, a queen on an empty 8x8 board has 27
+pub const MOBILITY_CAP: usize = 27;
+// centipawns per attacked square around a king
+pub const KING_SAFETY_WEIGHT: i32 = 15;
+
+// centipawns from white's point of view, a checkmate is worth MATE to the winner. the search scores
+// mates on its own before asking, so nearer ones win there
+pub fn material(state: &State) -> i32 {
+    if state.is_checkmate() {
+        return match state.to_move {
+            PieceColor::WHITE => -MATE,
+            PieceColor::BLACK => MATE,
+        };
+    }
+    
+    // kings are worth nothing here, custom pieces count with their registered value
+    state.get_pieces().iter()
+        .filter(|piece| piece.is_alive())
+        .map(|piece| piece.get_color().direction() * state.config.pieces.value(piece.get_piece_type()))
+        .sum()
+}
+
+// material plus the mobility and king safety terms, centipawns from white's point of view
+pub fn evaluate(state: &State) -> i32 {
+    let score = material(state);
+    if score.abs() == MATE {
+        return score;
+    }
+    
+    let mobility = mobility(state, PieceColor::WHITE) - mobility(state, PieceColor::BLACK);
+    let king_safety = king_safety(state, PieceColor::WHITE) - king_safety(state, PieceColor::BLACK);
+    score + (mobility * MOBILITY_WEIGHT) + king_safety
+}
+
+// pseudo legal moves color would have if it were its turn
+pub fn mobility(state: &State, color: PieceColor) -> i32 {
+    let state = state.clone().with_side_to_move(color);
+    let mut total = 0;
+    for piece in state.get_pieces().into_iter().filter(|piece| piece.is_alive() && (piece.get_color() == color)) {
+        let is_king = piece.get_piece_type() == PieceType::KING;
+        let mut generator = Generator::new(piece, state.clone());
+        let mut count = 0;
+        while !generator.is_depleated() && (count < MOBILITY_CAP) {
+            // a rook's castling move is the king's, count it once
+            if generator.next_pseudo().is_some_and(|piece_move| !piece_move.castling || is_king) {
+                count += 1;
+            }
+        }
+        total += count as i32;
+    }
+    total
+}
+
+// penalty for the squares around color's kings, and the ones they stand on, the other side attacks
+pub fn king_safety(state: &State, color: PieceColor) -> i32 {
+    let mut attacked = 0;
+    for king in state.get_royal_pieces(color) {
+        for x in -1..=1 {
+            for y in -1..=1 {
+                let square = state.wrap(king.get_position().saturating_add(Vec2::new(x, y)));
+                if state.is_in_bounds(square) && state.is_square_attacked(square, color.opposite()) {
+                    attacked += 1;
+                }
+            }
+        }
+    }
+    -attacked * KING_SAFETY_WEIGHT
+}
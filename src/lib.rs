@@ -1,4 +1,12 @@
 pub mod pieces;
 pub mod state;
 pub mod moves;
-pub mod config;
\ No newline at end of file
+pub mod config;
+pub mod zobrist;
+pub mod eval;
+pub mod search;
+pub mod attacks;
+pub mod perft;
+pub mod positions;
+pub mod pgn;
+pub mod prelude;
\ No newline at end of file
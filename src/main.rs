@@ -1,35 +1,55 @@
+use std::env;
+use std::process;
 use std::time::Instant;
 
-use glam::IVec2 as Vec2;
+use quasar::perft::perft_divide;
 use quasar::state::State;
-use quasar::moves::Generator;
+
+const USAGE: &str = "usage: quasar fen \"<FEN>\" perft <depth>
+       quasar fen \"<FEN>\" divide <depth>
+       quasar fen \"<FEN>\" legal";
 
 fn main() {
-    let state = State::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1".to_owned());
-    println!("{}", state);
-    
-    let piece = state.get_piece_at(Vec2::new(4, 1)).unwrap().clone();
-    let mut gen = Generator::new(piece, state);
-    let mut i: i128 = 0;
-    let start = Instant::now();
-    loop {
-        if i > 100 {
-            break;
-        }
-        i += 1;
-        let piece_move = gen.next_pseudo();
-        match piece_move {
-            None => {},
-            Some(_) => println!("{} {} {} {:?} {} {:?} {}",
-                piece_move.as_ref().unwrap().piece,
-                piece_move.as_ref().unwrap().start,
-                piece_move.as_ref().unwrap().end,
-                piece_move.as_ref().unwrap().promotion,
-                piece_move.as_ref().unwrap().castling,
-                piece_move.as_ref().unwrap().castling_target,
-                piece_move.as_ref().unwrap().en_passant,
-            )
-        }
+    let args: Vec<String> = env::args().skip(1).collect();
+    if let Err(error) = run(&args) {
+        eprintln!("{}", error);
+        eprintln!("{}", USAGE);
+        process::exit(1);
+    }
+}
+
+fn run(args: &[String]) -> Result<(), String> {
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    match args[..] {
+        ["fen", fen, "perft", depth] => {
+            let depth: usize = depth.parse().map_err(|_| format!("Invalid depth '{}'.", depth))?;
+            let start = Instant::now();
+            let nodes = State::perft_from_fen(fen, depth).map_err(|error| error.to_string())?;
+            println!("{} nodes in {:?}", nodes, start.elapsed());
+        },
+        ["fen", fen, "divide", depth] => {
+            let depth: usize = depth.parse().map_err(|_| format!("Invalid depth '{}'.", depth))?;
+            let state = State::from_fen(fen.to_owned()).map_err(|error| error.to_string())?;
+            let divided = perft_divide(&state, depth);
+            for (piece_move, nodes) in divided.iter() {
+                println!("{}: {}", piece_move.to_uci(&state).unwrap_or(piece_move.to_string()), nodes);
+            }
+            println!("{} nodes", divided.iter().map(|(_, nodes)| nodes).sum::<u64>());
+        },
+        ["fen", fen, "legal"] => {
+            let state = State::from_fen(fen.to_owned()).map_err(|error| error.to_string())?;
+            println!("{}", state);
+            // squares left of or below the a1 corner have no algebraic name
+            let moves: Vec<String> = state.get_legal_moves().iter()
+                .map(|piece_move| piece_move.to_uci(&state).unwrap_or(piece_move.to_string()))
+                .collect();
+            for piece_move in moves.iter() {
+                println!("{}", piece_move);
+            }
+            println!("{} legal moves", moves.len());
+        },
+        [] => return Err("No command given.".to_owned()),
+        _ => return Err(format!("Unknown command '{}'.", args.join(" "))),
     }
-    println!("{:?}", start.elapsed())
+    Ok(())
 }
@@ -1,10 +1,13 @@
+use core::cmp::Ordering;
 use core::fmt;
 
-use glam::IVec2 as Vec2;
+use glam::{I64Vec2, IVec2 as Vec2};
 use crate::pieces::{Piece, PieceColor, PieceType};
 use crate::state::State;
+use crate::config::Topology;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Move {
     pub start: Vec2,
     pub end: Vec2,
@@ -17,28 +20,164 @@ pub struct Move {
 }
 
 impl Move {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(start: Vec2, end: Vec2, piece: Piece, target: Option<Piece>,  promotion: Option<PieceType>, castling: bool, castling_target: Option<Piece>, en_passant: bool) -> Move {
         Move { start, end, piece, target, castling, castling_target, en_passant, promotion }
     }
     
     pub fn to_standard_notation(&self) -> Option<String>{
-        if self.start.x < 1 {
-            return None;
+        Some(format!("{}{}", position_to_algebraic(self.start)?, position_to_algebraic(self.end)?))
+    }
+    
+    // castling comes out as the king's move to where it lands in state, like e1g1. when that is a
+    // square the king could also step to, it stays the king taking its own rook
+    pub fn to_uci(&self, state: &State) -> Option<String> {
+        let promotion = self.promotion.map(|piece_type| piece_type.letter().to_string()).unwrap_or_default();
+        if self.castling {
+            let target = *self.castling_target.as_ref()?.get_position();
+            let (king, rook) = match self.piece.get_piece_type() {
+                PieceType::KING => (self.start, target),
+                _ => (target, self.start),
+            };
+            let (king_end, _) = state.castling_squares(king, rook);
+            let end = if (king_end - king).abs().max_element() > 1 { king_end } else { rook };
+            return Some(format!("{}{}", position_to_algebraic(king)?, position_to_algebraic(end)?));
         }
-        if self.start.y < 1 {
-            return None;
+        Some(format!("{}{}", self.to_standard_notation()?, promotion))
+    }
+    
+    // moves that cannot be played in state give neither check nor mate
+    pub fn gives_check(&self, state: &State) -> bool {
+        state.clone().try_make_move(self.clone())
+            .is_ok_and(|next| next.is_king_in_check(next.us()))
+    }
+    
+    pub fn gives_checkmate(&self, state: &State) -> bool {
+        state.clone().try_make_move(self.clone())
+            .is_ok_and(|next| next.is_checkmate())
+    }
+    
+    // standard algebraic notation of a move in state. like to_uci there is none for squares without
+    // an algebraic name, and none for moves that cannot be played there
+    pub fn to_san(&self, state: &State) -> Option<String> {
+        let next = state.clone().try_make_move(self.clone()).ok()?;
+        let suffix = match next.is_checkmate() {
+            true => "#",
+            false if next.is_king_in_check(next.us()) => "+",
+            false => "",
+        };
+        
+        if self.castling {
+            let target = self.castling_target.as_ref()?;
+            let (king, rook) = match self.piece.get_piece_type() {
+                PieceType::KING => (self.start, *target.get_position()),
+                _ => (*target.get_position(), self.start),
+            };
+            let side = if rook.x > king.x { "O-O" } else { "O-O-O" };
+            return Some(format!("{}{}", side, suffix));
         }
-        if self.start.x > 8 {
-            return None;
+        
+        let (start, end) = (position_to_algebraic(self.start)?, position_to_algebraic(self.end)?);
+        let (file, rank) = start.split_at(start.find(|symbol: char| symbol.is_ascii_digit())?);
+        let capture = self.en_passant || self.target.as_ref().is_some_and(|target| {
+            target.is_alive() && (target.get_color() != self.piece.get_color())
+        });
+        let mut san = String::new();
+        match self.piece.get_piece_type() {
+            PieceType::PAWN => {
+                if capture {
+                    san += file;
+                }
+            },
+            piece_type => {
+                san.push(state.config.pieces.letter(piece_type).to_ascii_uppercase());
+                // only as much of the start square as tells it apart from the other pieces of its kind
+                let others: Vec<Vec2> = state.get_legal_moves().into_iter()
+                    .filter(|other| (other.end == self.end) && (other.start != self.start) && (other.piece.get_piece_type() == piece_type))
+                    .map(|other| other.start)
+                    .collect();
+                if !others.is_empty() {
+                    if others.iter().all(|other| other.x != self.start.x) {
+                        san += file;
+                    }
+                    else if others.iter().all(|other| other.y != self.start.y) {
+                        san += rank;
+                    }
+                    else {
+                        san += &start;
+                    }
+                }
+            },
         }
-        if self.start.x > 8 {
-            return None;
+        if capture {
+            san.push('x');
         }
-        
-        let start_file = ((self.start.x - 1) as u8 + 'a' as u8) as char;
-        let end_file = ((self.end.x - 1) as u8 + 'a' as u8) as char;
-        
-        return Some(format!("{}{}{}{}", start_file, self.start.y, end_file, self.end.y))
+        san += &end;
+        if let Some(promotion) = self.promotion {
+            san.push('=');
+            san.push(state.config.pieces.letter(promotion).to_ascii_uppercase());
+        }
+        Some(san + suffix)
+    }
+}
+
+// files past 'z' continue like spreadsheet columns: z, aa, ab, ..., az, ba, ...
+pub fn position_to_algebraic(position: Vec2) -> Option<String> {
+    if (position.x < 1) || (position.y < 1) {
+        return None;
+    }
+    let mut file = String::new();
+    let mut x = position.x;
+    while x > 0 {
+        x -= 1;
+        file.insert(0, (b'a' + (x % 26) as u8) as char);
+        x /= 26;
+    }
+    Some(format!("{}{}", file, position.y))
+}
+
+pub fn algebraic_to_position(algebraic: &str) -> Option<Vec2> {
+    let split = algebraic.find(|symbol: char| !symbol.is_ascii_lowercase())?;
+    let (file, rank) = algebraic.split_at(split);
+    if file.is_empty() || !rank.chars().all(|symbol| symbol.is_ascii_digit()) {
+        return None;
+    }
+    let mut x: i32 = 0;
+    for symbol in file.chars() {
+        x = x.checked_mul(26)?.checked_add((symbol as u8 - b'a') as i32 + 1)?;
+    }
+    let y: i32 = rank.parse().ok()?;
+    if y < 1 {
+        return None;
+    }
+    Some(Vec2::new(x, y))
+}
+
+// by start square, then end square, each by file and then rank, then promotion by letter with none first.
+// on a standard board that is the order of the sorted uci strings. the moves of one position already
+// differ there, the rest of the fields only break ties so that equal in order means equal
+impl Ord for Move {
+    fn cmp(&self, other: &Move) -> Ordering {
+        let key = |piece_move: &Move| {
+            let (start, end) = (piece_move.start, piece_move.end);
+            let promotion = piece_move.promotion.map(|promotion| (promotion.letter(), promotion.index()));
+            (start.x, start.y, end.x, end.y, promotion, piece_move.castling, piece_move.en_passant)
+        };
+        let pieces = |piece_move: &Move| {
+            [Some(&piece_move.piece), piece_move.target.as_ref(), piece_move.castling_target.as_ref()].map(|piece| piece.map(piece_key))
+        };
+        key(self).cmp(&key(other)).then_with(|| pieces(self).cmp(&pieces(other)))
+    }
+}
+
+fn piece_key(piece: &Piece) -> (u32, bool, usize, i32, i32, bool, bool, bool) {
+    let position = piece.get_position();
+    (piece.get_id(), piece.get_color() == PieceColor::WHITE, piece.get_piece_type().index(), position.x, position.y, piece.has_moved(), piece.is_alive(), piece.is_royal())
+}
+
+impl PartialOrd for Move {
+    fn partial_cmp(&self, other: &Move) -> Option<Ordering> {
+        Some(self.cmp(other))
     }
 }
 
@@ -58,68 +197,76 @@ impl fmt::Display for Move {
             self.start.y,
             self.end.x,
             self.end.y,
-        )
+        )?;
+        if let Some(promotion) = self.promotion {
+            write!(f, "={}", promotion.letter().to_ascii_uppercase())?;
+        }
+        if self.en_passant {
+            write!(f, " ep")?;
+        }
+        // the short side is the one with the rook right of the king, whichever of the two is moving
+        if let Some(target) = self.castling.then_some(self.castling_target.as_ref()).flatten() {
+            let rook_is_right = match self.piece.get_piece_type() {
+                PieceType::KING => target.get_position().x > self.start.x,
+                _ => self.start.x > target.get_position().x,
+            };
+            write!(f, " {}", if rook_is_right { "O-O" } else { "O-O-O" })?;
+        }
+        Ok(())
     }
 }
 
+// cloning keeps the cursor, so a copy carries on from the same move as the original
+#[derive(Debug, Clone)]
 pub struct Generator {
     n: Vec<usize>,
     buffer: Vec<Move>,
     piece: Piece,
     state: State,
-    offsets: Option<Vec<Vec2>>,
+    offsets: Vec<Vec2>,
+    // offsets before this one are leaps, the rest are rides
+    leapers: usize,
+    in_check: Option<bool>,
+    // filled on the first legality check, pieces without moves never pay for it
+    pins: Option<Vec<Vec2>>,
+    // pins are only followed along straight and diagonal lines, other rides make every move simulated
+    pins_suffice: Option<bool>,
 }
 
 impl Generator {
     pub fn new(piece: Piece, state: State) -> Generator {
+        let offsets = state.config.pieces.base_offsets(piece.get_piece_type(), piece.get_color());
+        let leapers = state.config.pieces.leaper_offsets(piece.get_piece_type(), piece.get_color()).len();
+        
+        // one slot per direction, the king has 2 more for castling
         let n: usize = match piece.get_piece_type() {
-            PieceType::PAWN => {3} //move forward, left att, right att
-            PieceType::KNIGHT => {8}
-            PieceType::BISHOP => {4}
-            PieceType::ROOK => {4}
-            PieceType::QUEEN => {8}
-            PieceType::KING => {10} //8 directions and 2 castling directions
-            _ => {0}
+            PieceType::KING => offsets.len() + 2,
+            _ => offsets.len(),
         };
         
         let n = vec![0; n];
-        
-        let straight: Vec<Vec2> = vec![Vec2::new( 1,  0), Vec2::new(-1,  0), Vec2::new( 0, -1), Vec2::new( 0,  1)];
-        let diagonal: Vec<Vec2> = vec![Vec2::new( 1,  1), Vec2::new( 1, -1), Vec2::new(-1,  1), Vec2::new(-1, -1),];
-        let combined: Vec<Vec2> = [straight.clone(), diagonal.clone()].concat();
-        
-        let offsets: Option<Vec<Vec2>> = match piece.get_piece_type() {
-            PieceType::PAWN => {None} ,
-            PieceType::KNIGHT => {None},
-            PieceType::BISHOP => {Some(diagonal.clone())},
-            PieceType::ROOK => {Some(straight.clone())},
-            PieceType::QUEEN => {Some(combined.clone())},
-            PieceType::KING => {None},
-            _ => {None},
-        };
         let buffer = vec![];
-        Generator { n, buffer, piece, state, offsets }
+        Generator { n, buffer, piece, state, offsets, leapers, in_check: None, pins: None, pins_suffice: None }
+    }
+    
+    // lets callers generating for many pieces share one check test. a wrong flag lets illegal moves
+    // through, so only the crate's own move lists pass it
+    pub(crate) fn with_check(mut self, in_check: bool) -> Generator {
+        self.in_check = Some(in_check);
+        self
     }
     
     pub fn is_depleated(&self) -> bool {
-        let min_n = self.n.iter().min().unwrap().clone();
-        if min_n == usize::MAX {
-            true
-        }
-        else {
-            false
-        }
+        self.buffer.is_empty() && self.n.iter().all(|&r| r == usize::MAX)
     }
     
     fn is_in_bounds(&self, point: Vec2) -> bool {
-        let top_left = self.state.config.boundaries[0];
-        let bottom_right = self.state.config.boundaries[1];
-        if (point.x < bottom_right.x) && (point.x > top_left.x) {
-            if (point.y > bottom_right.y) && (point.y < top_left.y) {
-                return true;
-            }
-        }
-        false
+        self.state.is_in_bounds(point)
+    }
+    
+    // squares of friendly pieces are dropped before a move is even built for them
+    fn is_friendly(&self, point: Vec2) -> bool {
+        self.state.get_piece_at(point).is_some_and(|piece| piece.get_color() == self.piece.get_color())
     }
     
     pub fn reset(&mut self) {
@@ -129,25 +276,35 @@ impl Generator {
         }
     }
     
+    // false while a slider still has a ray to walk that nothing stops on an infinite board
+    pub fn remaining_is_bounded(&self) -> bool {
+        if (self.leapers == self.offsets.len()) || (self.state.config.topology != Topology::Infinite) {
+            return true;
+        }
+        let origin = self.piece.get_position().as_i64vec2();
+        let pieces = self.state.get_pieces();
+        self.offsets.iter().zip(self.n.iter())
+            .skip(self.leapers)
+            .filter(|(_, &r)| r != usize::MAX)
+            .all(|(direction, _)| pieces.iter().any(|piece| {
+                let relative = piece.get_position().as_i64vec2() - origin;
+                let distance = relative.abs().max_element();
+                piece.is_alive() && (distance > 0) && (direction.as_i64vec2() * distance == relative)
+            }))
+    }
+    
     fn next_pawn_offset(&mut self) -> Option<Move> {
-        if self.buffer.len() > 0 {
+        if !self.buffer.is_empty() {
             return self.buffer.pop();
         }
         //move forward, left att, right att
-        let offsets = match self.piece.get_color() {
-            PieceColor::WHITE => vec![Vec2::new(0, 1), Vec2::new(-1, 1), Vec2::new(1, 1)],
-            PieceColor::BLACK => vec![Vec2::new(0, -1), Vec2::new(1, -1), Vec2::new(-1, -1)]
-        };
-        let promotions: Vec<Option<PieceType>> = 
-            vec![
-                Some(PieceType::BISHOP),
-                Some(PieceType::KNIGHT),
-                Some(PieceType::ROOK),
-                Some(PieceType::QUEEN),
-                None,
-            ];
+        let offsets = self.offsets.clone();
+        let mut promotions: Vec<Option<PieceType>> = self.state.config.promotion_options.iter()
+            .map(|&promotion| Some(promotion))
+            .collect();
+        promotions.push(None);
         
-        for idx in 0..self.n.len() {
+        for (idx, offset) in offsets.iter().enumerate() {
             if self.n[idx] != 0 {
                 continue;
             }
@@ -158,16 +315,19 @@ impl Generator {
             };
             
             for mul in mul_iter {
-                let start = self.piece.get_position().clone();
-                let end = self.piece.get_position().clone() + (offsets[idx] * mul);
+                let start = *self.piece.get_position();
+                let end = self.state.wrap(self.piece.get_position().saturating_add(*offset * mul));
                 if !self.is_in_bounds(end) {
                     self.n[idx] = usize::MAX;
                     continue;
                 }
+                if self.is_friendly(end) {
+                    continue;
+                }
                 for promotion in promotions.iter() {
                     for en_passant in [true, false].iter() {
                         self.buffer.push(
-                            Move::new(start, end, self.piece.clone(), None, promotion.clone(), false, None, en_passant.to_owned())
+                            Move::new(start, end, self.piece.clone(), None, *promotion, false, None, en_passant.to_owned())
                         );
                     }
                 }
@@ -181,26 +341,16 @@ impl Generator {
     }
     
     fn next_knight_offset(&mut self) -> Option<Move> {
-        let offsets: Vec<Vec2> =
-            vec![
-                Vec2::new(-1,  2),
-                Vec2::new( 1,  2),
-                Vec2::new(-1, -2),
-                Vec2::new( 1, -2),
-                Vec2::new( 2, -1),
-                Vec2::new( 2,  1),
-                Vec2::new(-2, -1),
-                Vec2::new(-2,  1),
-            ];
-        for idx in 0..self.n.len() {
+        let offsets = self.offsets.clone();
+        for (idx, offset) in offsets.iter().enumerate() {
             if self.n[idx] != 0 {
                 self.n[idx] = usize::MAX;
                 continue;
             }
-            let start = self.piece.get_position().clone();
-            let end = self.piece.get_position().clone() + offsets[idx];
+            let start = *self.piece.get_position();
+            let end = self.state.wrap(self.piece.get_position().saturating_add(*offset));
             self.n[idx] = usize::MAX;
-            if !self.is_in_bounds(end) {
+            if !self.is_in_bounds(end) || self.is_friendly(end) {
                 return None;
             }
             return Some(Move::new(start, end, self.piece.clone(), None, None, false, None, false));
@@ -208,113 +358,78 @@ impl Generator {
         None
     }
     
-    fn next_bishop_offset(&mut self) -> Option<Move> {
-        let min_n = self.n.iter().min().unwrap().clone();
-        if min_n == usize::MAX {
+    // one square per call so a ray on an infinite board can be left unfinished,
+    // n holds how far each direction has been walked
+    fn next_sliding_offset(&mut self) -> Option<Move> {
+        let idx = self.n.iter().position(|&r| r != usize::MAX)?;
+        let steps = self.n[idx] + 1;
+        let start = *self.piece.get_position();
+        let reach = start.as_i64vec2() + self.offsets[idx].as_i64vec2() * steps as i64;
+        let end = self.state.wrap(reach.clamp(I64Vec2::splat(i32::MIN as i64), I64Vec2::splat(i32::MAX as i64)).as_ivec2());
+        // a ray around a torus ends when it comes back to where it started
+        if !self.is_in_bounds(end) || (end == start) {
+            self.n[idx] = usize::MAX;
             return None;
         }
+        self.n[idx] = steps;
         
-        let idx = self.n.iter().position(|&r| r == min_n).unwrap();
-        self.n[idx] += 1;
-        let offset = self.offsets.as_ref().unwrap()[idx] * self.n[idx] as i32;
-        let start = self.piece.get_position().clone();
-        let end = self.piece.get_position().clone() + offset;
-        if !self.is_in_bounds(end) {
-            self.n[idx] = usize::MAX;
-            return None;
+        let blocker = match self.state.get_piece_at(end) {
+            Some(blocker) => blocker.clone(),
+            None => return Some(Move::new(start, end, self.piece.clone(), None, None, false, None, false)),
+        };
+        self.n[idx] = usize::MAX;
+        if blocker.get_color() != self.piece.get_color() {
+            return Some(Move::new(start, end, self.piece.clone(), None, None, false, None, false));
+        }
+        // an unmoved rook running into its own king can castle with it
+        if (self.piece.get_piece_type() == PieceType::ROOK) && !self.piece.has_moved() && (blocker.get_piece_type() == PieceType::KING) {
+            return Some(Move::new(start, end, self.piece.clone(), None, None, true, Some(blocker), false));
         }
-        return Some(Move::new(start, end, self.piece.clone(), None, None, false, None, false));
+        None
+    }
+    
+    fn next_bishop_offset(&mut self) -> Option<Move> {
+        self.next_sliding_offset()
     }
 
     fn next_rook_offset(&mut self) -> Option<Move> {
-        if self.buffer.len() > 0 {
-            return self.buffer.pop()
-        }
-        let min_n = self.n.iter().min().unwrap().clone();
-        if min_n == usize::MAX {
-            return None;
-        }
-        
-        let idx = self.n.iter().position(|&r| r == min_n).unwrap();
-        self.n[idx] += 1;
-        let offset = self.offsets.as_ref().unwrap()[idx] * self.n[idx] as i32;
-        let start = self.piece.get_position().clone();
-        let end = self.piece.get_position().clone() + offset;
-        if !self.is_in_bounds(end) {
-            self.n[idx] = usize::MAX;
-            return None;
-        }
-        self.buffer.push(Move::new(start, end, self.piece.clone(), None, None, false, None, false));
-        if !self.piece.has_moved() {
-            let target_piece = self.state.get_piece_at(end);
-            if (!target_piece.is_none()) && (target_piece.unwrap().get_piece_type() == PieceType::KING) {
-                let target_piece = Some(target_piece.unwrap().clone());
-                self.buffer.push(Move::new(start, end, self.piece.clone(), None, None, true, target_piece, false));
-            }
-        }
-        self.buffer.pop()
+        self.next_sliding_offset()
     }
     
     fn next_queen_offset(&mut self) -> Option<Move> {
-        let min_n = self.n.iter().min().unwrap().clone();
-        if min_n == usize::MAX {
-            return None;
-        }
-        
-        let idx = self.n.iter().position(|&r| r == min_n).unwrap();
-        self.n[idx] += 1;
-        let offset = self.offsets.as_ref().unwrap()[idx] * self.n[idx] as i32;
-        let start = self.piece.get_position().clone();
-        let end = self.piece.get_position().clone() + offset;
-        if !self.is_in_bounds(end) {
-            self.n[idx] = usize::MAX;
-            return None;
-        }
-        return Some(Move::new(start, end, self.piece.clone(), None, None, false, None, false));
+        self.next_sliding_offset()
     }
     
     fn next_king_offset(&mut self) -> Option<Move> {
-        if self.buffer.len() > 0 {
+        if !self.buffer.is_empty() {
             return self.buffer.pop()
         }
-        let offsets: Vec<Vec2> =
-            vec![
-                Vec2::new( 1,  0),
-                Vec2::new(-1,  0),
-                Vec2::new( 0, -1),
-                Vec2::new( 0,  1),
-                Vec2::new( 1,  1),
-                Vec2::new( 1, -1),
-                Vec2::new(-1,  1),
-                Vec2::new(-1, -1),
-            ];
-        for idx in 0..offsets.len() {
+        let offsets = self.offsets.clone();
+        for (idx, offset) in offsets.iter().enumerate() {
             if self.n[idx] != 0 {
                 continue;
             }
-            let start = self.piece.get_position().clone();
-            let end = self.piece.get_position().clone() + offsets[idx];
-            if !self.is_in_bounds(end) {
-                self.n[idx] = usize::MAX;
+            let start = *self.piece.get_position();
+            let end = self.state.wrap(self.piece.get_position().saturating_add(*offset));
+            self.n[idx] = usize::MAX;
+            if !self.is_in_bounds(end) || self.is_friendly(end) {
                 continue;
             }
-            self.n[idx] = usize::MAX;
             
             self.buffer.push(Move::new(start, end, self.piece.clone(), None, None, false, None, false));
         }
         if !self.piece.has_moved() {
             let rooks = self.state.find(PieceType::ROOK, self.piece.get_color());
             for rook in rooks {
-                if !rook.has_moved() {
-                    let start = self.piece.get_position().clone();
-                    let end = rook.get_position().clone();
-                    let idx;
-                    if start.x - end.x < 0 {
-                        idx = 8;
+                if rook.is_alive() && !rook.has_moved() && (rook.get_position().y == self.piece.get_position().y) {
+                    let start = *self.piece.get_position();
+                    let end = *rook.get_position();
+                    let idx = if start.x - end.x < 0 {
+                        8
                     }
                     else {
-                        idx = 9;
-                    }
+                        9
+                    };
                     if self.n[idx] == 0 {
                         self.buffer.push(Move::new(start, end, self.piece.clone(), None, None, true, Some(rook.clone()), false));
                         self.n[idx] = usize::MAX;
@@ -322,9 +437,27 @@ impl Generator {
                 }
             }
         }
+        // castling is only looked at once
+        self.n[8] = usize::MAX;
+        self.n[9] = usize::MAX;
         self.buffer.pop()
     }
     
+    // the leaps come first, one square each, then the rides are walked like a slider's
+    fn next_custom_offset(&mut self) -> Option<Move> {
+        let idx = self.n.iter().position(|&r| r != usize::MAX)?;
+        if idx >= self.leapers {
+            return self.next_sliding_offset();
+        }
+        self.n[idx] = usize::MAX;
+        let start = *self.piece.get_position();
+        let end = self.state.wrap(start.saturating_add(self.offsets[idx]));
+        if !self.is_in_bounds(end) || (end == start) || self.is_friendly(end) {
+            return None;
+        }
+        Some(Move::new(start, end, self.piece.clone(), None, None, false, None, false))
+    }
+    
     fn next_offset(&mut self) -> Option<Move> {
         match self.piece.get_piece_type() {
             PieceType::NULL => None,
@@ -334,21 +467,20 @@ impl Generator {
             PieceType::ROOK => self.next_rook_offset(),
             PieceType::QUEEN => self.next_queen_offset(),
             PieceType::KING => self.next_king_offset(),
+            PieceType::CUSTOM(_) => self.next_custom_offset(),
         }
     }
     
     fn is_color_correct(&self) -> bool {
-        return self.piece.get_color() == self.state.to_move;
+        self.piece.get_color() == self.state.to_move
     }
     
     fn check_pawn_offset(&self, offset_move: &Move) -> bool {
-        let offset = offset_move.end - offset_move.start;
+        let offset = self.state.displacement(offset_move.start, offset_move.end);
         
         // check color based movement
-        let mul = match self.piece.get_color() {
-            PieceColor::WHITE => 1,
-            PieceColor::BLACK => -1 };
-        if offset.y * mul < 1 {
+        let forward = self.piece.get_color().forward();
+        if offset.y * forward.y < 1 {
             return false;
         }
         
@@ -357,25 +489,46 @@ impl Generator {
             return false;
         }
         
+        // pushes need empty squares, including the one jumped over
+        if offset.x == 0 {
+            if offset_move.target.is_some() {
+                return false;
+            }
+            if offset.y.abs() > 1 {
+                let passed = self.state.wrap(offset_move.start + forward);
+                if self.state.get_piece_at(passed).is_some() {
+                    return false;
+                }
+            }
+        }
+        
         // check if promotion available
         if !self.state.config.promotion_lines.contains(&offset_move.end.y) {
-            if !offset_move.promotion.is_none() {
+            if offset_move.promotion.is_some() {
                 return false;
             }
         }
         else {
-            if offset_move.promotion.is_none() {
-                return false;
+            match offset_move.promotion {
+                Some(promotion) if self.state.config.allows_promotion(promotion) => {},
+                _ => return false,
             }
         }
         
         // check attacks
-        // enpassant TODO
         if offset.x != 0 {
-            let attacked_piece = self.state.get_piece_at(offset_move.end);
-            match attacked_piece {
-                Some(_) => {},
-                None => return false
+            match offset_move.target {
+                Some(_) => {
+                    if offset_move.en_passant {
+                        return false;
+                    }
+                },
+                None => {
+                    // only an en passant capture lands on an empty square
+                    if !offset_move.en_passant || (self.state.get_en_passant_target() != Some(offset_move.end)) {
+                        return false;
+                    }
+                }
             }
         }
         
@@ -387,17 +540,17 @@ impl Generator {
     }
     
     fn check_diagonal_offset(&mut self, offset_move: &Move) -> bool {
-        let mut offset = offset_move.end - offset_move.start;
+        let mut offset = self.state.displacement(offset_move.start, offset_move.end);
         if offset.abs().max_element() == 0 {
             return false;
         }
         offset /= offset.abs().max_element();
         
         // path blocked, dont generate more moves in that direction
-        if !offset_move.target.is_none() {
-            let local_offsets = self.offsets.clone().unwrap();
-            for idx in 0..local_offsets.len() {
-                if offset == local_offsets[idx] {
+        if offset_move.target.is_some() {
+            let local_offsets = self.offsets.clone();
+            for (idx, local_offset) in local_offsets.iter().enumerate() {
+                if offset == *local_offset {
                     self.n[idx] = usize::MAX;
                     break;
                 }
@@ -413,17 +566,17 @@ impl Generator {
     }
 
     fn check_horizontal_offset(&mut self, offset_move: &Move) -> bool {
-        let mut offset = offset_move.end - offset_move.start;
+        let mut offset = self.state.displacement(offset_move.start, offset_move.end);
         if offset.abs().max_element() == 0 {
             return false;
         }
         offset /= offset.abs().max_element();
         
         // path blocked, dont generate more moves in that direction
-        if !offset_move.target.is_none() {
-            let local_offsets = self.offsets.clone().unwrap();
-            for idx in 0..local_offsets.len() {
-                if offset == local_offsets[idx] {
+        if offset_move.target.is_some() {
+            let local_offsets = self.offsets.clone();
+            for (idx, local_offset) in local_offsets.iter().enumerate() {
+                if offset == *local_offset {
                     self.n[idx] = usize::MAX;
                     break;
                 }
@@ -440,31 +593,46 @@ impl Generator {
     
     fn check_king_offset(&self, offset_move: &Move) -> bool {
         if offset_move.castling {
-            let mut offset = offset_move.end - offset_move.start;
-            offset /= offset.abs().x;
-            let mut checked_point = offset_move.start + offset;
-            loop {
-                if checked_point == offset_move.end {
-                    break;
-                }
-                let found_piece = self.state.get_piece_at(checked_point);
-                if (!found_piece.is_none()) && (found_piece.unwrap().get_piece_type() != PieceType::ROOK) && (!found_piece.unwrap().has_moved()) {
-                    return false;
-                }
-                checked_point += offset;
+            let rook = match &offset_move.castling_target {
+                Some(rook) => *rook.get_position(),
+                None => return false,
+            };
+            let king = offset_move.start;
+            let (king_end, rook_end) = self.state.castling_squares(king, rook);
+            if !self.state.is_in_bounds(king_end) || self.is_castling_through_check(king, king_end) {
+                return false;
             }
+            let low = king.x.min(rook.x).min(king_end.x).min(rook_end.x);
+            let high = king.x.max(rook.x).max(king_end.x).max(rook_end.x);
+            // everything the king and rook cross or land on must be empty apart from the two of them
+            return self.state.get_pieces().iter().all(|piece| {
+                let position = *piece.get_position();
+                !piece.is_alive() || (position == king) || (position == rook)
+                    || (position.y != king.y) || (position.x < low) || (position.x > high)
+            });
         }
         true
     }
     
+    // the king may not castle out of, through or into check
+    fn is_castling_through_check(&self, king: Vec2, king_end: Vec2) -> bool {
+        let enemy = self.piece.get_color().opposite();
+        let step = Vec2::new((king_end.x - king.x).signum(), 0);
+        let mut square = king;
+        loop {
+            if self.state.is_square_attacked(square, enemy) {
+                return true;
+            }
+            if square == king_end {
+                return false;
+            }
+            square += step;
+        }
+    }
+    
     pub fn next_pseudo(&mut self) -> Option<Move> {
-        let offset_move = self.next_offset();
-        
         // offset exists?
-        if offset_move.is_none() {
-            return None;
-        }
-        let mut offset_move = offset_move.unwrap();
+        let mut offset_move = self.next_offset()?;
         
         // correct color to move?
         if !self.is_color_correct() {
@@ -472,9 +640,8 @@ impl Generator {
         }
 
         // set target piece
-        let attacked_piece = self.state.get_piece_at(offset_move.end);
-        if !attacked_piece.is_none() {
-            offset_move.target = Some(attacked_piece.unwrap().clone());
+        if let Some(attacked_piece) = self.state.get_piece_at(offset_move.end) {
+            offset_move.target = Some(attacked_piece.clone());
         }
         
         // correct offset?
@@ -487,22 +654,21 @@ impl Generator {
             PieceType::ROOK => self.check_horizontal_offset(&offset_move),
             PieceType::QUEEN => self.check_diagonal_offset(&offset_move) || self.check_horizontal_offset(&offset_move),
             PieceType::KING => self.check_king_offset(&offset_move),
+            PieceType::CUSTOM(_) => true,
         };
         if !correct_offset {
             return None;
         }
         
         // only pawns can promote
-        if !offset_move.promotion.is_none() && (offset_move.piece.get_piece_type() != PieceType::PAWN) {
+        if offset_move.promotion.is_some() && (offset_move.piece.get_piece_type() != PieceType::PAWN) {
             return None;
         }
         
         // is target a friendly piece?
-        if !offset_move.target.is_none() {
-            if offset_move.piece.get_color() == offset_move.target.clone().unwrap().get_color() {
-                if !offset_move.castling {
-                    return None;
-                }
+        if let Some(target) = &offset_move.target {
+            if (offset_move.piece.get_color() == target.get_color()) && !offset_move.castling {
+                return None;
             }
         }
         
@@ -513,17 +679,7 @@ impl Generator {
             if offset_move.start.x == offset_move.end.x {
                 return None;
             }
-            if self.state.previous_move.is_none() {
-                return None;
-            }
-            let prev_move = self.state.previous_move.as_ref().unwrap().clone();
-            if prev_move.piece.get_piece_type() != PieceType::PAWN {
-                return None;
-            }
-            if (prev_move.start - prev_move.end).abs().y != 2 {
-                return None;
-            }
-            if (offset_move.piece.get_position() - prev_move.piece.get_position()).abs().x != 1 {
+            if self.state.get_en_passant_target() != Some(offset_move.end) {
                 return None;
             }
         }
@@ -531,7 +687,42 @@ impl Generator {
         Some(offset_move)
     }
     
-    //pub fn next(&self) -> Move {
-    //    
-    //}
+    fn leaves_king_in_check(&mut self, piece_move: &Move) -> bool {
+        let color = self.piece.get_color();
+        let in_check = *self.in_check.get_or_insert_with(|| self.state.is_king_in_check(color));
+        // without check, a non royal piece can only expose its king by leaving a pin line,
+        // en passant and castling move more than one piece so they are always simulated
+        // pin lines are not followed around a torus or along odd rides, there every move is simulated
+        let pins_suffice = *self.pins_suffice.get_or_insert_with(|| {
+            !matches!(self.state.config.topology, Topology::Toroidal { .. }) && !self.state.rides_off_lines(color.opposite())
+        });
+        if !in_check && pins_suffice && !self.piece.is_royal() && !piece_move.en_passant && !piece_move.castling {
+            let pins = self.pins.get_or_insert_with(|| self.state.pin_directions(*self.piece.get_position()));
+            let step = piece_move.end - piece_move.start;
+            return !pins.iter().all(|pin| pin.perp_dot(step) == 0);
+        }
+        let color = piece_move.piece.get_color();
+        let state = self.state.clone().simulate_move(piece_move.clone());
+        state.is_king_in_check(color)
+    }
+}
+
+impl Iterator for Generator {
+    type Item = Move;
+    
+    // pseudo legal moves that would leave the own king in check are skipped.
+    // on an infinite board a slider with an open ray does not run out, bound it with take
+    // or check remaining_is_bounded before collecting everything
+    fn next(&mut self) -> Option<Move> {
+        while !self.is_depleated() {
+            let piece_move = match self.next_pseudo() {
+                Some(piece_move) => piece_move,
+                None => continue,
+            };
+            if !self.leaves_king_in_check(&piece_move) {
+                return Some(piece_move);
+            }
+        }
+        None
+    }
 }
\ No newline at end of file
@@ -0,0 +1,110 @@
+use std::ops::AddAssign;
+use std::thread;
+
+use crate::moves::Move;
+use crate::pieces::PieceType;
+use crate::state::State;
+
+pub fn perft(state: &State, depth: usize) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+    state.legal_moves_iter()
+        .map(|piece_move| perft(&state.clone().simulate_move(piece_move), depth - 1))
+        .sum()
+}
+
+// the nodes below each root move in get_legal_moves order, they add up to perft at the same depth.
+// depth 0 has no moves to split the single node by
+pub fn perft_divide(state: &State, depth: usize) -> Vec<(Move, u64)> {
+    if depth == 0 {
+        return vec![];
+    }
+    state.get_legal_moves().into_iter()
+        .map(|piece_move| {
+            let nodes = perft(&state.clone().simulate_move(piece_move.clone()), depth - 1);
+            (piece_move, nodes)
+        })
+        .collect()
+}
+
+// root moves are dealt out round robin, each thread walks its subtrees on its own copy of the state
+pub fn perft_parallel(state: &State, depth: usize, threads: usize) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+    let moves = state.get_legal_moves();
+    let threads = threads.clamp(1, moves.len().max(1));
+    let moves = &moves;
+    thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads)
+            .map(|worker| scope.spawn(move || {
+                moves.iter().skip(worker).step_by(threads)
+                    .map(|piece_move| perft(&state.clone().simulate_move(piece_move.clone()), depth - 1))
+                    .sum::<u64>()
+            }))
+            .collect();
+        workers.into_iter().map(|worker| worker.join().expect("perft worker panicked")).sum()
+    })
+}
+
+// counts are taken at the leaves like in the usual reference tables, en passant counts as a capture too
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PerftStats {
+    pub nodes: u64,
+    pub captures: u64,
+    pub en_passant: u64,
+    pub castles: u64,
+    pub promotions: u64,
+    pub checks: u64,
+    pub checkmates: u64,
+}
+
+impl AddAssign for PerftStats {
+    fn add_assign(&mut self, other: PerftStats) {
+        self.nodes += other.nodes;
+        self.captures += other.captures;
+        self.en_passant += other.en_passant;
+        self.castles += other.castles;
+        self.promotions += other.promotions;
+        self.checks += other.checks;
+        self.checkmates += other.checkmates;
+    }
+}
+
+pub fn perft_detailed(state: &State, depth: usize) -> PerftStats {
+    let mut stats = PerftStats::default();
+    if depth == 0 {
+        stats.nodes = 1;
+        return stats;
+    }
+    for piece_move in state.legal_moves_iter() {
+        let next = state.clone().simulate_move(piece_move.clone());
+        if depth > 1 {
+            stats += perft_detailed(&next, depth - 1);
+            continue;
+        }
+        stats.nodes += 1;
+        // castling targets the own rook, that is not a capture
+        let capture = piece_move.target.as_ref().is_some_and(|target| target.get_color() != piece_move.piece.get_color());
+        if capture || piece_move.en_passant {
+            stats.captures += 1;
+        }
+        if piece_move.en_passant {
+            stats.en_passant += 1;
+        }
+        if piece_move.castling && (piece_move.piece.get_piece_type() == PieceType::KING) {
+            stats.castles += 1;
+        }
+        if piece_move.promotion.is_some() {
+            stats.promotions += 1;
+        }
+        if next.is_king_in_check(next.us()) {
+            stats.checks += 1;
+            if next.is_checkmate() {
+                stats.checkmates += 1;
+            }
+        }
+    }
+    stats
+}
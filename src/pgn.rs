@@ -0,0 +1,196 @@
+use crate::moves::{algebraic_to_position, Move};
+use crate::pieces::PieceType;
+use crate::state::{GameResult, State, START_FEN};
+
+// the seven tag roster without the result, unknown values are written as the standard asks
+pub const UNKNOWN_TAGS: [(&str, &str); 6] = [
+    ("Event", "?"),
+    ("Site", "?"),
+    ("Date", "????.??.??"),
+    ("Round", "?"),
+    ("White", "?"),
+    ("Black", "?"),
+];
+
+// export format lines stay below 80 characters
+const LINE_WIDTH: usize = 79;
+
+pub fn result_token(result: GameResult) -> &'static str {
+    match result {
+        GameResult::WhiteWins => "1-0",
+        GameResult::BlackWins => "0-1",
+        GameResult::Draw => "1/2-1/2",
+        GameResult::Ongoing => "*",
+    }
+}
+
+// the game that led to state, played forward from the position its history starts at. games that did not
+// start from the standard position get SetUp and FEN tags. passed turns have no notation and are refused
+pub fn export_pgn(state: &State) -> Result<String, String> {
+    if state.has_passed_turns() {
+        return Err("A passed turn cannot be written to PGN.".to_owned());
+    }
+    let start = state.start_position();
+    let moves = state.history();
+
+    let result = result_token(state.result());
+    let mut pgn = String::new();
+    for (name, value) in UNKNOWN_TAGS {
+        pgn += &format!("[{} \"{}\"]\n", name, value);
+    }
+    pgn += &format!("[Result \"{}\"]\n", result);
+    let fen = start.to_fen();
+    if fen != START_FEN {
+        pgn += &format!("[SetUp \"1\"]\n[FEN \"{}\"]\n", fen);
+    }
+    pgn += "\n";
+
+    let mut tokens = vec![];
+    let mut position = start;
+    for (ply, piece_move) in moves.into_iter().enumerate() {
+        let san = piece_move.to_san(&position).ok_or(format!("Move {} has no algebraic notation.", ply + 1))?;
+        if position.side_to_move_is_white() {
+            tokens.push(format!("{}.", position.full_moves));
+        }
+        else if ply == 0 {
+            tokens.push(format!("{}...", position.full_moves));
+        }
+        tokens.push(san);
+        position = position.try_make_move(piece_move)?;
+    }
+    tokens.push(result.to_owned());
+
+    let mut line = String::new();
+    for token in tokens {
+        if !line.is_empty() && (line.len() + 1 + token.len() > LINE_WIDTH) {
+            pgn += &line;
+            pgn += "\n";
+            line.clear();
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line += &token;
+    }
+    pgn += &line;
+    pgn += "\n";
+    Ok(pgn)
+}
+
+// the position after every ply of the game's main line, starting from its FEN tag if it has one.
+// comments, annotations and variations are skipped
+pub fn import_pgn(pgn: &str) -> Result<Vec<State>, String> {
+    let (tags, movetext) = split_pgn(pgn)?;
+    let mut state = match tags.iter().find(|(name, _)| name == "FEN") {
+        Some((_, fen)) => State::from_fen(fen.clone()).map_err(|error| error.to_string())?,
+        None => State::default(),
+    };
+    
+    let mut states = vec![];
+    for token in movetext.split_whitespace() {
+        // move numbers may be glued to the move that follows them
+        let san = token.trim_start_matches(|symbol: char| symbol.is_ascii_digit() || (symbol == '.'));
+        if san.is_empty() || san.starts_with('$') || ["1-0", "0-1", "1/2-1/2", "*"].contains(&token) {
+            continue;
+        }
+        let ply = states.len() + 1;
+        let piece_move = find_san(&state, san).map_err(|error| format!("Move {} '{}' at ply {}: {}", state.full_moves, san, ply, error))?;
+        state = state.try_make_move(piece_move)?;
+        states.push(state.clone());
+    }
+    Ok(states)
+}
+
+// tag pairs and the movetext with comments and variations taken out
+fn split_pgn(pgn: &str) -> Result<(Vec<(String, String)>, String), String> {
+    let mut tags = vec![];
+    let mut movetext = String::new();
+    let mut chars = pgn.chars();
+    let mut variations = 0;
+    while let Some(symbol) = chars.next() {
+        match symbol {
+            '{' => {
+                chars.by_ref().find(|&symbol| symbol == '}').ok_or("Unclosed comment.")?;
+                movetext.push(' ');
+            },
+            ';' => {
+                chars.by_ref().find(|&symbol| symbol == '\n');
+                movetext.push(' ');
+            },
+            '(' => variations += 1,
+            ')' if variations > 0 => {
+                variations -= 1;
+                movetext.push(' ');
+            },
+            ')' => return Err("Unopened variation.".to_owned()),
+            _ if variations > 0 => {},
+            '[' => {
+                let tag: String = chars.by_ref().take_while(|&symbol| symbol != ']').collect();
+                let (name, value) = tag.trim().split_once(char::is_whitespace).ok_or(format!("Malformed tag [{}].", tag))?;
+                tags.push((name.to_owned(), value.trim().trim_matches('"').to_owned()));
+            },
+            symbol => movetext.push(symbol),
+        }
+    }
+    if variations > 0 {
+        return Err("Unclosed variation.".to_owned());
+    }
+    Ok((tags, movetext))
+}
+
+// the legal move a SAN token stands for, extra disambiguation and missing check marks are fine
+fn find_san(state: &State, san: &str) -> Result<Move, String> {
+    let san = san.trim_end_matches(['+', '#', '!', '?']);
+    if !san.is_ascii() {
+        return Err("not a move".to_owned());
+    }
+    let legal = state.get_legal_moves();
+    let candidates: Vec<Move> = match san {
+        "O-O" | "0-0" | "O-O-O" | "0-0-0" => {
+            let short = san.len() == 3;
+            legal.into_iter()
+                .filter(|piece_move| piece_move.castling && (piece_move.piece.get_piece_type() == PieceType::KING))
+                .filter(|piece_move| piece_move.castling_target.as_ref().is_some_and(|rook| (rook.get_position().x > piece_move.start.x) == short))
+                .collect()
+        },
+        _ => {
+            let (rest, promotion) = match san.split_once('=') {
+                Some((rest, promotion)) => {
+                    let letter = promotion.chars().next().ok_or("missing promotion piece")?;
+                    (rest, Some(state.config.pieces.from_letter(letter)))
+                },
+                None => (san, None),
+            };
+            let (piece_type, rest) = match rest.chars().next() {
+                Some(letter) if letter.is_ascii_uppercase() => (state.config.pieces.from_letter(letter), &rest[1..]),
+                _ => (PieceType::PAWN, rest),
+            };
+            // the end square is the last file letter and rank, whatever comes before it narrows down the start.
+            // files past z take two letters and are not told apart from a start file here
+            let digits = rest.len() - rest.trim_end_matches(|symbol: char| symbol.is_ascii_digit()).len();
+            let split = rest.len().checked_sub(digits + 1).ok_or("no end square")?;
+            let end = algebraic_to_position(&rest[split..]).ok_or("no end square")?;
+            let from = rest[..split].strip_suffix('x').unwrap_or(&rest[..split]);
+            let from_rank = from.trim_start_matches(|symbol: char| symbol.is_ascii_lowercase());
+            let from_file = &from[..from.len() - from_rank.len()];
+            let from_x = match from_file {
+                "" => None,
+                file => Some(algebraic_to_position(&format!("{}1", file)).ok_or("bad start file")?.x),
+            };
+            let from_y = match from_rank {
+                "" => None,
+                rank => Some(rank.parse::<i32>().map_err(|_| "bad start rank")?),
+            };
+            legal.into_iter()
+                .filter(|piece_move| !piece_move.castling && (piece_move.end == end) && (piece_move.promotion == promotion))
+                .filter(|piece_move| piece_move.piece.get_piece_type() == piece_type)
+                .filter(|piece_move| from_x.is_none_or(|x| piece_move.start.x == x) && from_y.is_none_or(|y| piece_move.start.y == y))
+                .collect()
+        },
+    };
+    match &candidates[..] {
+        [piece_move] => Ok(piece_move.clone()),
+        [] => Err("not a legal move".to_owned()),
+        _ => Err("ambiguous, more than one piece can make it".to_owned()),
+    }
+}
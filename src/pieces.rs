@@ -1,7 +1,8 @@
 use glam::IVec2 as Vec2;
 use core::fmt;
 
-#[derive(Debug, PartialEq, Clone, Copy)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum PieceColor {
     BLACK,
     WHITE
@@ -14,9 +15,43 @@ impl PieceColor {
             true => PieceColor::WHITE
         }
     }
+    
+    pub fn opposite(&self) -> PieceColor {
+        match self {
+            PieceColor::BLACK => PieceColor::WHITE,
+            PieceColor::WHITE => PieceColor::BLACK,
+        }
+    }
+    
+    // the way this side's pawns walk along the ranks
+    pub fn direction(&self) -> i32 {
+        match self {
+            PieceColor::BLACK => -1,
+            PieceColor::WHITE => 1,
+        }
+    }
+    
+    // one pawn push, everything that moves pawns goes through this
+    pub fn forward(&self) -> Vec2 {
+        Vec2::new(0, self.direction())
+    }
+}
+
+// true is white, like in from_bool
+impl From<bool> for PieceColor {
+    fn from(value: bool) -> PieceColor {
+        PieceColor::from_bool(value)
+    }
+}
+
+impl From<PieceColor> for bool {
+    fn from(color: PieceColor) -> bool {
+        color == PieceColor::WHITE
+    }
 }
 
-#[derive(Debug, PartialEq, Clone, Copy)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum PieceType {
     NULL,
     PAWN,
@@ -24,7 +59,123 @@ pub enum PieceType {
     BISHOP,
     ROOK,
     QUEEN,
-    KING
+    KING,
+    // index into the PieceRegistry of the game's config
+    CUSTOM(usize),
+}
+
+// a fairy piece, leapers jump straight to their offset and riders repeat theirs until blocked.
+// offsets are given for white, black's are mirrored across the ranks
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CustomPiece {
+    pub name: String,
+    pub letter: char,
+    pub value: i32,
+    pub leaper_offsets: Vec<Vec2>,
+    pub rider_offsets: Vec<Vec2>,
+}
+
+// the custom pieces of one game, PieceType::CUSTOM(idx) is the piece at idx. it travels with the
+// config, so built in types answer for themselves and custom ones are looked up here
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PieceRegistry {
+    pieces: Vec<CustomPiece>,
+}
+
+impl PieceRegistry {
+    // the letter is what FEN and the board display use, it has to be free
+    pub fn register(&mut self, piece: CustomPiece) -> Result<PieceType, String> {
+        let letter = piece.letter.to_ascii_lowercase();
+        if piece.leaper_offsets.iter().chain(piece.rider_offsets.iter()).any(|offset| *offset == Vec2::ZERO) {
+            return Err(format!("{} has an offset that does not move.", piece.name));
+        }
+        if !letter.is_ascii_alphabetic() || (letter == 'x') || (self.from_letter(letter) != PieceType::NULL) {
+            return Err(format!("Letter '{}' cannot be used for {}.", piece.letter, piece.name));
+        }
+        self.pieces.push(CustomPiece { letter, ..piece });
+        Ok(PieceType::CUSTOM(self.pieces.len() - 1))
+    }
+    
+    pub fn get(&self, piece_type: PieceType) -> Option<&CustomPiece> {
+        match piece_type {
+            PieceType::CUSTOM(idx) => self.pieces.get(idx),
+            _ => None,
+        }
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.pieces.is_empty()
+    }
+    
+    pub fn from_letter(&self, letter: char) -> PieceType {
+        let letter = letter.to_ascii_lowercase();
+        match PieceType::from_letter(letter) {
+            PieceType::NULL => self.pieces.iter()
+                .position(|piece| piece.letter == letter)
+                .map_or(PieceType::NULL, PieceType::CUSTOM),
+            piece_type => piece_type,
+        }
+    }
+    
+    pub fn letter(&self, piece_type: PieceType) -> char {
+        self.get(piece_type).map_or(piece_type.letter(), |piece| piece.letter)
+    }
+    
+    // white pieces in upper case, like in a FEN
+    pub fn symbol(&self, piece: &Piece) -> char {
+        let letter = self.letter(piece.get_piece_type());
+        match piece.get_color() {
+            PieceColor::WHITE => letter.to_ascii_uppercase(),
+            PieceColor::BLACK => letter,
+        }
+    }
+    
+    pub fn name(&self, piece_type: PieceType) -> String {
+        self.get(piece_type).map_or(type_to_name(piece_type), |piece| piece.name.to_lowercase())
+    }
+    
+    pub fn value(&self, piece_type: PieceType) -> i32 {
+        self.get(piece_type).map_or(piece_type.value(), |piece| piece.value)
+    }
+    
+    pub fn leaper_offsets(&self, piece_type: PieceType, color: PieceColor) -> Vec<Vec2> {
+        match self.get(piece_type) {
+            Some(piece) => mirrored(&piece.leaper_offsets, color),
+            None => piece_type.leaper_offsets(color),
+        }
+    }
+    
+    pub fn rider_offsets(&self, piece_type: PieceType, color: PieceColor) -> Vec<Vec2> {
+        match self.get(piece_type) {
+            Some(piece) => mirrored(&piece.rider_offsets, color),
+            None => piece_type.rider_offsets(color),
+        }
+    }
+    
+    // the furthest any piece lands in one leap, in king steps. the knight's 2 at least
+    pub fn longest_leap(&self) -> i32 {
+        self.pieces.iter()
+            .flat_map(|piece| piece.leaper_offsets.iter())
+            .map(|leap| leap.x.saturating_abs().max(leap.y.saturating_abs()))
+            .fold(2, i32::max)
+    }
+    
+    // leaps first, then rides
+    pub fn base_offsets(&self, piece_type: PieceType, color: PieceColor) -> Vec<Vec2> {
+        match self.get(piece_type) {
+            Some(piece) => [mirrored(&piece.leaper_offsets, color), mirrored(&piece.rider_offsets, color)].concat(),
+            None => piece_type.base_offsets(color),
+        }
+    }
+}
+
+fn mirrored(offsets: &[Vec2], color: PieceColor) -> Vec<Vec2> {
+    match color {
+        PieceColor::WHITE => offsets.to_vec(),
+        PieceColor::BLACK => offsets.iter().map(|offset| Vec2::new(offset.x, -offset.y)).collect(),
+    }
 }
 
 impl PieceType {
@@ -40,11 +191,107 @@ impl PieceType {
             _ => PieceType::NULL,
         }
     }
+    
+    // centipawns, the king is priceless and counts as nothing.
+    // custom pieces know nothing about themselves here, their values and moves are in the PieceRegistry
+    pub fn value(&self) -> i32 {
+        match self {
+            PieceType::NULL => 0,
+            PieceType::PAWN => 100,
+            PieceType::KNIGHT => 320,
+            PieceType::BISHOP => 330,
+            PieceType::ROOK => 500,
+            PieceType::QUEEN => 900,
+            PieceType::KING => 0,
+            PieceType::CUSTOM(_) => 0,
+        }
+    }
+    
+    // built in types count from 0 to 6 in declaration order, custom ones follow
+    pub fn index(&self) -> usize {
+        match self {
+            PieceType::NULL => 0,
+            PieceType::PAWN => 1,
+            PieceType::KNIGHT => 2,
+            PieceType::BISHOP => 3,
+            PieceType::ROOK => 4,
+            PieceType::QUEEN => 5,
+            PieceType::KING => 6,
+            PieceType::CUSTOM(idx) => 7 + idx,
+        }
+    }
+    
+    // one step in each direction the piece moves, sliders repeat theirs until blocked.
+    // a pawn has its push first and then its two captures, the double step is the push taken twice
+    pub fn base_offsets(&self, color: PieceColor) -> Vec<Vec2> {
+        let straight = [Vec2::new(1, 0), Vec2::new(-1, 0), Vec2::new(0, -1), Vec2::new(0, 1)];
+        let diagonal = [Vec2::new(1, 1), Vec2::new(1, -1), Vec2::new(-1, 1), Vec2::new(-1, -1)];
+        match self {
+            PieceType::NULL => vec![],
+            // the push, then the captures to the pawn's left and right
+            PieceType::PAWN => {
+                let forward = color.forward();
+                vec![forward, forward + forward.perp(), forward - forward.perp()]
+            },
+            PieceType::KNIGHT => vec![
+                Vec2::new(-1, 2), Vec2::new(1, 2), Vec2::new(-1, -2), Vec2::new(1, -2),
+                Vec2::new(2, -1), Vec2::new(2, 1), Vec2::new(-2, -1), Vec2::new(-2, 1),
+            ],
+            PieceType::BISHOP => diagonal.to_vec(),
+            PieceType::ROOK => straight.to_vec(),
+            PieceType::QUEEN | PieceType::KING => [straight, diagonal].concat(),
+            PieceType::CUSTOM(_) => vec![],
+        }
+    }
+    
+    // the base offsets taken a single step at a time
+    pub fn leaper_offsets(&self, color: PieceColor) -> Vec<Vec2> {
+        match self {
+            _ if self.is_sliding() => vec![],
+            _ => self.base_offsets(color),
+        }
+    }
+    
+    // the base offsets repeated until something is in the way
+    pub fn rider_offsets(&self, color: PieceColor) -> Vec<Vec2> {
+        match self {
+            _ if self.is_sliding() => self.base_offsets(color),
+            _ => vec![],
+        }
+    }
+    
+    pub fn is_sliding(&self) -> bool {
+        matches!(self, PieceType::BISHOP | PieceType::ROOK | PieceType::QUEEN)
+    }
+    
+    pub fn letter(&self) -> char {
+        match self {
+            PieceType::NULL => 'x',
+            PieceType::PAWN => 'p',
+            PieceType::KNIGHT => 'n',
+            PieceType::BISHOP => 'b',
+            PieceType::ROOK => 'r',
+            PieceType::QUEEN => 'q',
+            PieceType::KING => 'k',
+            PieceType::CUSTOM(_) => 'x',
+        }
+    }
+    
+    pub fn from_letter(letter: char) -> PieceType {
+        match letter.to_ascii_lowercase() {
+            'p' => PieceType::PAWN,
+            'n' => PieceType::KNIGHT,
+            'b' => PieceType::BISHOP,
+            'r' => PieceType::ROOK,
+            'q' => PieceType::QUEEN,
+            'k' => PieceType::KING,
+            _ => PieceType::NULL,
+        }
+    }
 }
 
 pub fn name_to_type(name: String) -> PieceType {
-    let name: &str = &format!("{}", name.to_lowercase());
-    match name {
+    match name.to_lowercase().as_str() {
         "pawn" => PieceType::PAWN,
         "knight" => PieceType::KNIGHT,
         "bishop" => PieceType::BISHOP,
@@ -64,55 +311,51 @@ pub fn type_to_name(piece_type: PieceType) -> String {
         PieceType::ROOK => "rook".to_owned(),
         PieceType::QUEEN => "queen".to_owned(),
         PieceType::KING => "king".to_owned(),
+        PieceType::CUSTOM(_) => "null".to_owned(),
     }
 }
 
 pub fn name_to_symbol(name: String) -> char {
-    let name: &str = &format!("{}", name.to_lowercase());
-    match name {
-        "pawn" => 'p',
-        "knight" => 'n',
-        "bishop" => 'b',
-        "rook" => 'r',
-        "queen" => 'q',
-        "king" => 'k',
-        _ => 'x',
-    }
+    name_to_type(name).letter()
 }
 
 pub fn symbol_to_name(symbol: char) -> String {
-    let symbol = symbol.to_ascii_lowercase();
-    match symbol {
-        'p' => "pawn".to_owned(),
-        'n' => "knight".to_owned(),
-        'b' => "bishop".to_owned(),
-        'r' => "rook".to_owned(),
-        'q' => "queen".to_owned(),
-        'k' => "king".to_owned(),
-        _ => "null".to_owned(),
-    }
+    type_to_name(PieceType::from_letter(symbol))
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Piece {
+    id: u32,
     color: PieceColor,
     has_moved: bool,
     is_alive: bool,
     piece_type: PieceType,
     position: Vec2,
+    royal: bool,
 }
 
 impl Piece {
     pub fn new(piece_color: PieceColor, piece_type: PieceType, position: Vec2) -> Piece {
-        Piece {color: piece_color, has_moved: false, is_alive: true, piece_type, position}
+        let royal = piece_type == PieceType::KING;
+        Piece {id: 0, color: piece_color, has_moved: false, is_alive: true, piece_type, position, royal}
+    }
+    
+    // ids are handed out by the state that owns the piece, in the order pieces are added
+    pub fn get_id(&self) -> u32 {
+        self.id
+    }
+    
+    pub fn set_id(&mut self, id: u32) {
+        self.id = id
     }
     
     pub fn get_color(&self) -> PieceColor {
-        return self.color
+        self.color
     }
     
     pub fn has_moved(&self) -> bool {
-        return self.has_moved
+        self.has_moved
     }
     
     pub fn moved(&mut self) {
@@ -120,15 +363,32 @@ impl Piece {
     }
     
     pub fn is_alive(&self) -> bool {
-        return self.is_alive
+        self.is_alive
     }
     
     pub fn capture(&mut self) {
         self.is_alive = false
     }
     
+    pub fn revive(&mut self) {
+        self.is_alive = true
+    }
+    
+    pub fn is_royal(&self) -> bool {
+        self.royal
+    }
+    
+    pub fn set_royal(&mut self, royal: bool) {
+        self.royal = royal
+    }
+    
     pub fn get_piece_type(&self) -> PieceType {
-        return self.piece_type
+        self.piece_type
+    }
+    
+    // the piece keeps its id, so history and undo still find it
+    pub fn promote(&mut self, piece_type: PieceType) {
+        self.piece_type = piece_type
     }
     
     pub fn get_name(&self) -> String {
@@ -136,7 +396,7 @@ impl Piece {
     }
     
     pub fn get_symbol(&self) -> char {
-        let symbol = name_to_symbol(type_to_name(self.get_piece_type()));
+        let symbol = self.get_piece_type().letter();
         match self.get_color() {
             PieceColor::BLACK => symbol,
             PieceColor::WHITE => symbol.to_ascii_uppercase()
@@ -144,7 +404,7 @@ impl Piece {
     }
     
     pub fn get_position(&self) -> &Vec2 {
-        return &self.position
+        &self.position
     }
     
     pub fn set_position(&mut self, pos: Vec2) {
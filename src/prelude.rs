@@ -0,0 +1,10 @@
+// coordinates are glam's IVec2, used as Vec2 all over the crate
+pub use glam::IVec2 as Vec2;
+
+pub use crate::config::{Config, Topology};
+pub use crate::moves::{algebraic_to_position, position_to_algebraic, Generator, Move};
+pub use crate::perft::{perft, perft_detailed, perft_divide, perft_parallel, PerftStats};
+pub use crate::pgn::{export_pgn, import_pgn};
+pub use crate::pieces::{Piece, PieceColor, PieceType};
+pub use crate::search::{best_move, best_move_timed, Search};
+pub use crate::state::{CaptureMode, GameResult, ParseError, State, StateBuilder, START_FEN};
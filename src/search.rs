@@ -0,0 +1,141 @@
+use std::time::{Duration, Instant};
+
+use crate::eval::{material, MATE};
+use crate::moves::Move;
+use crate::pieces::PieceColor;
+use crate::state::State;
+
+const INFINITY: i32 = i32::MAX - 1;
+
+// score from the point of view of the side to move
+fn evaluate(state: &State) -> i32 {
+    match state.to_move {
+        PieceColor::WHITE => material(state),
+        PieceColor::BLACK => -material(state),
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Search {
+    pub nodes: u64,
+    pub ordering: bool,
+    deadline: Option<Instant>,
+    stopped: bool,
+}
+
+impl Search {
+    pub fn new() -> Search {
+        Search { nodes: 0, ordering: true, deadline: None, stopped: false }
+    }
+    
+    // polled every 1024 nodes so the clock does not dominate the search
+    fn should_stop(&mut self) -> bool {
+        if !self.stopped && self.nodes.is_multiple_of(1024) {
+            if let Some(deadline) = self.deadline {
+                self.stopped = Instant::now() >= deadline;
+            }
+        }
+        self.stopped
+    }
+    
+    fn legal_moves(&self, state: &State) -> Vec<Move> {
+        let mut moves = state.get_legal_moves();
+        if self.ordering {
+            state.order_moves(&mut moves);
+        }
+        moves
+    }
+    
+    fn negamax(&mut self, state: &State, depth: u32, ply: i32, mut alpha: i32, beta: i32) -> i32 {
+        self.nodes += 1;
+        if self.should_stop() {
+            return 0;
+        }
+        // at the horizon one legal move is enough to know the game goes on
+        let moves = match depth {
+            0 => match state.legal_moves_iter().next() {
+                Some(_) => return evaluate(state),
+                None => vec![],
+            },
+            _ => self.legal_moves(state),
+        };
+        if moves.is_empty() {
+            // mates closer to the root score higher
+            return match state.is_king_in_check(state.us()) {
+                true => -(MATE - ply),
+                false => 0,
+            };
+        }
+        
+        for piece_move in moves {
+            let child = state.clone().simulate_move(piece_move);
+            let score = -self.negamax(&child, depth - 1, ply + 1, -beta, -alpha);
+            if score >= beta {
+                return beta;
+            }
+            if score > alpha {
+                alpha = score;
+            }
+        }
+        alpha
+    }
+    
+    pub fn best_move(&mut self, state: &State, depth: u32) -> Option<(Move, i32)> {
+        if depth == 0 {
+            return None;
+        }
+        let mut best: Option<(Move, i32)> = None;
+        let mut alpha = -INFINITY;
+        for piece_move in self.legal_moves(state) {
+            if self.deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                self.stopped = true;
+            }
+            if self.stopped {
+                return None;
+            }
+            let child = state.clone().simulate_move(piece_move.clone());
+            let score = -self.negamax(&child, depth - 1, 1, -INFINITY, -alpha);
+            if self.stopped {
+                return None;
+            }
+            if best.is_none() || (score > alpha) {
+                alpha = score;
+                best = Some((piece_move, score));
+            }
+        }
+        best
+    }
+    
+    // deepens until the budget runs out, keeping the result of the last completed depth
+    pub fn best_move_timed(&mut self, state: &State, budget: Duration) -> Option<(Move, u32)> {
+        let start = Instant::now();
+        self.deadline = None;
+        self.stopped = false;
+        
+        // the first depth always completes so there is a move to return
+        let mut best = (self.best_move(state, 1)?.0, 1);
+        self.deadline = Some(start + budget);
+        for depth in 2.. {
+            match self.best_move(state, depth) {
+                Some((piece_move, _)) => best = (piece_move, depth),
+                None => break,
+            }
+        }
+        self.deadline = None;
+        Some(best)
+    }
+}
+
+impl Default for Search {
+    fn default() -> Search {
+        Search::new()
+    }
+}
+
+pub fn best_move(state: &State, depth: u32) -> Option<(Move, i32)> {
+    Search::new().best_move(state, depth)
+}
+
+pub fn best_move_timed(state: &State, budget: Duration) -> Option<(Move, u32)> {
+    Search::new().best_move_timed(state, budget)
+}
@@ -1,86 +1,872 @@
 use core::fmt;
-use std::usize;
+use core::hash::{BuildHasherDefault, Hash, Hasher};
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::sync::{Arc, OnceLock};
 
-use crate::{moves::Move, pieces::{name_to_type, symbol_to_name, Piece, PieceColor, PieceType}, config::Config};
-use glam::IVec2 as Vec2;
+use crate::{attacks::AttackMap, moves::{algebraic_to_position, position_to_algebraic, Generator, Move}, pieces::{Piece, PieceColor, PieceRegistry, PieceType}, config::{Config, Topology}, zobrist::ZobristTable};
+use glam::{I64Vec2, IVec2 as Vec2};
+
+pub const START_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct State {
     pieces: Vec<Piece>,
     pub to_move: PieceColor,
     pub half_moves: usize,
     pub full_moves: usize,
     pub config: Config,
-    pub previous_move: Option<Move>
+    pub previous_move: Option<Move>,
+    // square a pawn skipped with its double step on the last move
+    en_passant_square: Option<Vec2>,
+    // every move made since the position was set up, also where repetitions are counted from
+    history: History,
+    // the position the history was played from, none while it is empty
+    #[cfg_attr(feature = "serde", serde(default))]
+    start: Option<Arc<State>>,
+    declared_result: Option<GameResult>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    capture_mode: CaptureMode,
+    // squares of the live pieces to their index in pieces, rebuilt whenever pieces change.
+    // left out of serialized states, lookups fall back to scanning until it is built again
+    #[cfg_attr(feature = "serde", serde(skip))]
+    occupancy: Option<HashMap<Vec2, usize, BuildHasherDefault<SquareHasher>>>,
+}
+
+// squares are two small integers, the default hasher is far slower than the lookups it replaces
+#[derive(Default)]
+struct SquareHasher(u64);
+
+impl Hasher for SquareHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+    
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.write_u8(*byte);
+        }
+    }
+    
+    fn write_u8(&mut self, value: u8) {
+        self.write_u32(value as u32);
+    }
+    
+    fn write_u32(&mut self, value: u32) {
+        self.0 = (self.0.rotate_left(5) ^ value as u64).wrapping_mul(0x51_7C_C1_B7_27_22_0A_95);
+    }
+    
+    fn write_i32(&mut self, value: i32) {
+        self.write_u32(value as u32);
+    }
 }
 
+// a move together with what it overwrote, enough to take it back. a passed turn has no move
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct PlayedMove {
+    piece_move: Option<Move>,
+    captured: Option<u32>,
+    to_move: PieceColor,
+    half_moves: usize,
+    full_moves: usize,
+    previous_move: Option<Move>,
+    en_passant_square: Option<Vec2>,
+    // the position the move was made from. after a capture, pawn move, castling or a lost castling right
+    // none of the positions before can come again, repetitions are only looked for up to there
+    hash: u64,
+    irreversible: bool,
+}
+
+// the played moves newest first. a state made from another shares its older moves,
+// so neither cloning a state nor making a move copies the game
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(from = "Vec<PlayedMove>", into = "Vec<PlayedMove>"))]
+struct History(Option<Arc<(PlayedMove, History)>>);
+
+impl History {
+    fn push(&self, played: PlayedMove) -> History {
+        History(Some(Arc::new((played, self.clone()))))
+    }
+    
+    fn split_last(&self) -> Option<(&PlayedMove, &History)> {
+        self.0.as_deref().map(|(played, earlier)| (played, earlier))
+    }
+    
+    fn iter(&self) -> impl Iterator<Item = &PlayedMove> {
+        std::iter::successors(self.split_last(), |(_, earlier)| earlier.split_last()).map(|(played, _)| played)
+    }
+}
+
+// unlinks the moves one at a time, dropping a long game recursively could run out of stack
+impl Drop for History {
+    fn drop(&mut self) {
+        let mut next = self.0.take();
+        while let Some(node) = next {
+            next = Arc::try_unwrap(node).ok().and_then(|(_, mut earlier)| earlier.0.take());
+        }
+    }
+}
+
+impl From<Vec<PlayedMove>> for History {
+    fn from(moves: Vec<PlayedMove>) -> History {
+        moves.into_iter().fold(History::default(), |history, played| history.push(played))
+    }
+}
+
+impl From<History> for Vec<PlayedMove> {
+    fn from(history: History) -> Vec<PlayedMove> {
+        let mut moves: Vec<PlayedMove> = history.iter().cloned().collect();
+        moves.reverse();
+        moves
+    }
+}
+
+// captured pieces stay in the piece list so unmake_move can bring them back,
+// a long game that never takes moves back can drop them and keep the scans short
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CaptureMode {
+    #[default]
+    KeepForUndo,
+    Compact,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum GameResult {
+    WhiteWins,
+    BlackWins,
+    Draw,
+    Ongoing,
+}
+
+// what went wrong reading a FEN, EPD or ascii board
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseError {
+    WrongFieldCount { found: usize, fen: String },
+    BadRankCount { expected: i32, found: usize, placement: String },
+    BadRankWidth { rank: String, found: i32, expected: i32 },
+    InvalidPiece { symbol: char, rank: String },
+    InvalidSquare { symbol: char, row: String },
+    KingCount { color: PieceColor, found: usize },
+    PawnOnBackRank(Vec2),
+    InvalidColor(String),
+    InvalidCastling { symbol: char, castling: String },
+    DuplicateCastling { symbol: char, castling: String },
+    InvalidEnPassant(String),
+    EnPassantRank { square: String, rank: i32 },
+    NoEnPassantPawn(String),
+    InvalidClock(String),
+    InvalidMoveNumber(String),
+    MissingEpdFields(String),
+    UnterminatedQuote(String),
+    DuplicateOpcode(String),
+    // validation keeps going after the first problem
+    Several(Vec<ParseError>),
+}
+
+impl From<Vec<ParseError>> for ParseError {
+    fn from(mut errors: Vec<ParseError>) -> ParseError {
+        match errors.len() {
+            1 => errors.remove(0),
+            _ => ParseError::Several(errors),
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::WrongFieldCount { found, fen } => write!(f, "Expected 4 to 6 fields but found {} in '{}'.", found, fen),
+            ParseError::BadRankCount { expected, found, placement } => write!(f, "Expected {} ranks but found {} in '{}'.", expected, found, placement),
+            ParseError::BadRankWidth { rank, found, expected } => write!(f, "Rank '{}' covers {} files instead of {}.", rank, found, expected),
+            ParseError::InvalidPiece { symbol, rank } => write!(f, "Invalid piece '{}' in rank '{}'.", symbol, rank),
+            ParseError::InvalidSquare { symbol, row } => write!(f, "Invalid square '{}' in row '{}'.", symbol, row),
+            ParseError::KingCount { color, found } => write!(f, "Expected one {:?} king but found {}.", color, found),
+            ParseError::PawnOnBackRank(square) => {
+                let name = position_to_algebraic(*square).unwrap_or(square.to_string());
+                write!(f, "Pawn on {} stands on the first or last rank.", name)
+            },
+            ParseError::InvalidColor(side) => write!(f, "Invalid side to move '{}'.", side),
+            ParseError::InvalidCastling { symbol, castling } => write!(f, "Invalid castling symbol '{}' in '{}'.", symbol, castling),
+            ParseError::DuplicateCastling { symbol, castling } => write!(f, "Duplicate castling symbol '{}' in '{}'.", symbol, castling),
+            ParseError::InvalidEnPassant(square) => write!(f, "Invalid en passant square '{}'.", square),
+            ParseError::EnPassantRank { square, rank } => write!(f, "En passant square '{}' is not on rank {}.", square, rank),
+            ParseError::NoEnPassantPawn(square) => write!(f, "No pawn can be captured en passant on '{}'.", square),
+            ParseError::InvalidClock(field) => write!(f, "Invalid halfmove clock '{}'.", field),
+            ParseError::InvalidMoveNumber(field) => write!(f, "Invalid fullmove number '{}'.", field),
+            ParseError::MissingEpdFields(epd) => write!(f, "EPD '{}' needs four board fields.", epd),
+            ParseError::UnterminatedQuote(epd) => write!(f, "Unterminated quote in EPD '{}'.", epd),
+            ParseError::DuplicateOpcode(opcode) => write!(f, "Duplicate EPD opcode '{}'.", opcode),
+            ParseError::Several(errors) => {
+                let messages: Vec<String> = errors.iter().map(ParseError::to_string).collect();
+                write!(f, "{}", messages.join(" "))
+            },
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
 impl State {
-    pub fn from_fen(fen: String) -> State {
+    pub fn try_default() -> Result<State, String> {
+        State::from_start_fen(START_FEN)
+    }
+    
+    // stands in for default when games or perft should start somewhere else, see positions for a few
+    pub fn from_start_fen(fen: &str) -> Result<State, String> {
+        State::from_fen(fen.to_owned()).map_err(|error| error.to_string())
+    }
+    
+    // plays the moves from the start position, each one has to be legal where it is played
+    pub fn replay(moves: &[Move]) -> Result<State, String> {
+        let mut state = State::try_default()?;
+        for (ply, played) in moves.iter().enumerate() {
+            let legal = state.legal_moves_from(played.start).into_iter().find(|piece_move| {
+                (piece_move.end == played.end) && (piece_move.promotion == played.promotion) && (piece_move.castling == played.castling)
+            });
+            match legal {
+                Some(legal) => state = state.make_move(legal),
+                None => return Err(format!("Move {} from {} to {} is not legal.", ply + 1, played.start, played.end)),
+            }
+        }
+        Ok(state)
+    }
+    
+    // moves in the order they were played, positions loaded from FEN start with none. passed turns are left out
+    pub fn history(&self) -> Vec<Move> {
+        let mut moves: Vec<Move> = self.history.iter().filter_map(|played| played.piece_move.clone()).collect();
+        moves.reverse();
+        moves
+    }
+    
+    // the position before the first move of the history, the state itself if nothing was played yet.
+    // kept as it was, so it can be played forward even after captured pieces were compacted away
+    pub fn start_position(&self) -> State {
+        match &self.start {
+            Some(start) => start.as_ref().clone(),
+            None => self.clone(),
+        }
+    }
+    
+    pub(crate) fn has_passed_turns(&self) -> bool {
+        self.history.iter().any(|played| played.piece_move.is_none())
+    }
+    
+    // position ids follow the standard numbering, 518 is the classical setup
+    pub fn chess960(position_id: u16) -> Result<State, String> {
+        if position_id >= 960 {
+            return Err(format!("Chess960 position id {} is out of range.", position_id));
+        }
+        let mut rank: [Option<char>; 8] = [None; 8];
+        let id = position_id as usize;
+        rank[(id % 4) * 2 + 1] = Some('B');
+        rank[((id / 4) % 4) * 2] = Some('B');
+        
+        let empty = |rank: &[Option<char>; 8]| -> Vec<usize> {
+            (0..8).filter(|&file| rank[file].is_none()).collect()
+        };
+        let queen = empty(&rank)[(id / 16) % 6];
+        rank[queen] = Some('Q');
+        
+        let knights = [(0, 1), (0, 2), (0, 3), (0, 4), (1, 2), (1, 3), (1, 4), (2, 3), (2, 4), (3, 4)][id / 96];
+        let free = empty(&rank);
+        rank[free[knights.0]] = Some('N');
+        rank[free[knights.1]] = Some('N');
+        
+        // king always sits between the rooks
+        for (file, symbol) in empty(&rank).into_iter().zip(['R', 'K', 'R']) {
+            rank[file] = Some(symbol);
+        }
+        let white: String = rank.iter().flatten().collect();
+        let fen = format!("{}/pppppppp/8/8/8/8/PPPPPPPP/{} w KQkq - 0 1", white.to_lowercase(), white);
+        State::from_fen(fen).map_err(|error| error.to_string())
+    }
+    
+    pub fn from_fen(fen: String) -> Result<State, ParseError> {
+        State::from_fen_sized(fen, 8, 8)
+    }
+    
+    pub fn from_fen_sized(fen: String, width: i32, height: i32) -> Result<State, ParseError> {
+        State::from_fen_with_pieces(fen, width, height, Arc::default())
+    }
+    
+    // custom pieces are written with the letters they were registered under
+    pub fn from_fen_with_pieces(fen: String, width: i32, height: i32, registry: Arc<PieceRegistry>) -> Result<State, ParseError> {
+        State::validate(&fen, width, height, false, &registry)?;
+        let fields: Vec<&str> = fen.split_whitespace().collect();
+        let (mut pieces, _) = State::parse_placement(fields[0], width, height, &registry);
+        
+        if let Some(castling) = fields.get(2) {
+            State::apply_castling_rights(&mut pieces, castling)?;
+        }
+        
+        let to_move = match fields[1] {
+            "b" => PieceColor::BLACK,
+            _ => PieceColor::WHITE,
+        };
+        let half_moves = fields.get(4).map_or(Ok(0), |field| field.parse().map_err(|_| ParseError::InvalidClock(field.to_string())))?;
+        let full_moves = fields.get(5).map_or(Ok(1), |field| field.parse().map_err(|_| ParseError::InvalidMoveNumber(field.to_string())))?;
+        let previous_move = State::en_passant_move(&pieces, fields[3], to_move)?;
+        let en_passant_square = algebraic_to_position(fields[3]);
+        let promotion_lines = vec![1, height];
+        let boundaries = [Vec2::new(0, height + 1), Vec2::new(width + 1, 0)];
+        let config = Config { pieces: registry, ..Config::new(boundaries, promotion_lines) };
+        
+        Ok(State::indexed(State { pieces, to_move, half_moves, full_moves, config, previous_move, en_passant_square, history: History::default(), start: None, declared_result: None, capture_mode: CaptureMode::default(), occupancy: None }))
+    }
+    
+    // standard chess only, pawns on the first or last rank are rejected
+    pub fn from_fen_strict(fen: String) -> Result<State, ParseError> {
+        State::validate_fen(&fen, true)?;
+        State::from_fen(fen)
+    }
+    
+    // leaf nodes of the move tree below a position, for checking the generator against known counts
+    pub fn perft_from_fen(fen: &str, depth: usize) -> Result<u64, ParseError> {
+        Ok(crate::perft::perft(&State::from_fen(fen.to_owned())?, depth))
+    }
+    
+    pub fn validate_fen(fen: &str, strict: bool) -> Result<(), Vec<ParseError>> {
+        State::validate_fen_sized(fen, 8, 8, strict)
+    }
+    
+    // collects every problem instead of stopping at the first one, the clocks may be left out.
+    // strict adds the rules of standard chess that variants may want to bend
+    pub fn validate_fen_sized(fen: &str, width: i32, height: i32, strict: bool) -> Result<(), Vec<ParseError>> {
+        State::validate(fen, width, height, strict, &PieceRegistry::default())
+    }
+    
+    fn validate(fen: &str, width: i32, height: i32, strict: bool, registry: &PieceRegistry) -> Result<(), Vec<ParseError>> {
+        let fields: Vec<&str> = fen.split_whitespace().collect();
+        if !(4..=6).contains(&fields.len()) {
+            return Err(vec![ParseError::WrongFieldCount { found: fields.len(), fen: fen.to_owned() }]);
+        }
+        let (mut pieces, mut errors) = State::parse_placement(fields[0], width, height, registry);
+        
+        if strict {
+            for pawn in pieces.iter().filter(|piece| piece.get_piece_type() == PieceType::PAWN) {
+                let square = *pawn.get_position();
+                if (square.y == 1) || (square.y == height) {
+                    errors.push(ParseError::PawnOnBackRank(square));
+                }
+            }
+        }
+        
+        for color in [PieceColor::WHITE, PieceColor::BLACK] {
+            let kings = pieces.iter()
+                .filter(|piece| (piece.get_piece_type() == PieceType::KING) && (piece.get_color() == color))
+                .count();
+            if kings != 1 {
+                errors.push(ParseError::KingCount { color, found: kings });
+            }
+        }
+        
+        let to_move = match fields[1] {
+            "w" => PieceColor::WHITE,
+            "b" => PieceColor::BLACK,
+            side => {
+                errors.push(ParseError::InvalidColor(side.to_owned()));
+                PieceColor::WHITE
+            },
+        };
+        if let Err(error) = State::apply_castling_rights(&mut pieces, fields[2]) {
+            errors.push(error);
+        }
+        if fields[3] != "-" {
+            // the square a pawn just skipped over, right behind that pawn
+            let skipped_rank = match to_move {
+                PieceColor::WHITE => height - 2,
+                PieceColor::BLACK => 3,
+            };
+            match algebraic_to_position(fields[3]) {
+                Some(square) if (square.y != skipped_rank) || (square.x > width) => {
+                    errors.push(ParseError::EnPassantRank { square: fields[3].to_owned(), rank: skipped_rank });
+                },
+                Some(_) => {
+                    if let Err(error) = State::en_passant_move(&pieces, fields[3], to_move) {
+                        errors.push(error);
+                    }
+                },
+                None => errors.push(ParseError::InvalidEnPassant(fields[3].to_owned())),
+            }
+        }
+        if let Some(field) = fields.get(4).filter(|field| field.parse::<usize>().is_err()) {
+            errors.push(ParseError::InvalidClock(field.to_string()));
+        }
+        if let Some(field) = fields.get(5).filter(|field| field.parse::<usize>().is_err()) {
+            errors.push(ParseError::InvalidMoveNumber(field.to_string()));
+        }
+        
+        if errors.is_empty() {
+            Ok(())
+        }
+        else {
+            Err(errors)
+        }
+    }
+    
+    // files run from 1 to width and ranks from 1 to height, empty runs may take several digits
+    fn parse_placement(placement: &str, width: i32, height: i32, registry: &PieceRegistry) -> (Vec<Piece>, Vec<ParseError>) {
         let mut pieces = vec![];
-        let mut x: i32 = 1;
-        let mut y: i32 = 8;
+        let mut errors = vec![];
+        let ranks: Vec<&str> = placement.split('/').collect();
+        if ranks.len() != height as usize {
+            errors.push(ParseError::BadRankCount { expected: height, found: ranks.len(), placement: placement.to_owned() });
+        }
         
-        for symbol in fen.chars() {
-            if symbol == ' ' {
-                break;
+        for (row, rank) in ranks.iter().enumerate() {
+            let y = height - row as i32;
+            let mut x: i32 = 1;
+            let mut empty: i32 = 0;
+            for symbol in rank.chars() {
+                if let Some(digit) = symbol.to_digit(10) {
+                    empty = empty * 10 + digit as i32;
+                    continue;
+                }
+                x += empty;
+                empty = 0;
+                
+                let piece_type = registry.from_letter(symbol);
+                if piece_type == PieceType::NULL {
+                    errors.push(ParseError::InvalidPiece { symbol, rank: rank.to_string() });
+                    x += 1;
+                    continue;
+                }
+                let piece_color = if symbol.is_lowercase() {
+                    PieceColor::BLACK
+                }
+                else {
+                    PieceColor::WHITE
+                };
+                let mut piece = Piece::new(piece_color, piece_type, Vec2::new(x, y));
+                piece.set_id(pieces.len() as u32);
+                // a pawn away from its home rank can no longer double step
+                let home_rank = match piece_color {
+                    PieceColor::WHITE => 2,
+                    PieceColor::BLACK => height - 1,
+                };
+                if (piece_type == PieceType::PAWN) && (y != home_rank) {
+                    piece.moved();
+                }
+                pieces.push(piece);
+                x += 1;
             }
-            if symbol == '/' {
-                y -= 1;
-                x = 1;
-                continue;
+            x += empty;
+            if x - 1 != width {
+                errors.push(ParseError::BadRankWidth { rank: rank.to_string(), found: x - 1, expected: width });
             }
-            if symbol.is_digit(10) {
-                x += symbol as i32 - '0' as i32;
-                continue;
+        }
+        (pieces, errors)
+    }
+    
+    // rows of piece letters and dots from the top rank down, the same grid Display prints
+    pub fn from_ascii(board: &str, to_move: PieceColor) -> Result<State, ParseError> {
+        let rows: Vec<&str> = board.lines().map(str::trim).filter(|row| !row.is_empty()).collect();
+        let mut ranks = vec![];
+        for row in rows.iter() {
+            let mut rank = String::new();
+            let mut empty = 0;
+            for symbol in row.chars() {
+                if symbol == '.' {
+                    empty += 1;
+                    continue;
+                }
+                if !symbol.is_ascii_alphabetic() {
+                    return Err(ParseError::InvalidSquare { symbol, row: row.to_string() });
+                }
+                if empty > 0 {
+                    rank += &empty.to_string();
+                    empty = 0;
+                }
+                rank.push(symbol);
             }
-            let piece_color;
-            if symbol.is_lowercase() {
-                piece_color = PieceColor::BLACK;
+            if empty > 0 {
+                rank += &empty.to_string();
             }
-            else {
-                piece_color = PieceColor::WHITE;
+            ranks.push(rank);
+        }
+        let width = rows.first().map_or(0, |row| row.chars().count()) as i32;
+        let side = match to_move {
+            PieceColor::WHITE => "w",
+            PieceColor::BLACK => "b",
+        };
+        State::from_fen_sized(format!("{} {} - - 0 1", ranks.join("/"), side), width, rows.len() as i32)
+    }
+    
+    // the four board fields of a FEN followed by `opcode operands;` operations, hmvc and fmvn fill the clocks
+    pub fn from_epd(epd: String) -> Result<(State, HashMap<String, String>), ParseError> {
+        let epd = epd.trim();
+        let mut board = vec![];
+        let mut rest = epd;
+        for _ in 0..4 {
+            let field_end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+            if field_end == 0 {
+                return Err(ParseError::MissingEpdFields(epd.to_owned()));
             }
-            let piece_type: PieceType = name_to_type(symbol_to_name(symbol));
-            pieces.push(Piece::new(piece_color, piece_type, Vec2::new(x, y)));
-            x += 1
+            board.push(&rest[..field_end]);
+            rest = rest[field_end..].trim_start();
         }
         
-        let to_move = PieceColor::WHITE;
-        let half_moves = 0;
-        let full_moves = 0;
-        let promotion_lines = vec![1,8];
-        let boundaries = [Vec2::new(0, 9), Vec2::new(9, 0)];
-        let config = Config::new(boundaries, promotion_lines);
+        let mut operations = HashMap::new();
+        let mut operation = String::new();
+        let mut quoted = false;
+        for symbol in rest.chars() {
+            match symbol {
+                '"' => {
+                    quoted = !quoted;
+                    operation.push(symbol);
+                },
+                ';' if !quoted => {
+                    State::push_epd_operation(&mut operations, &operation)?;
+                    operation.clear();
+                },
+                _ => operation.push(symbol),
+            }
+        }
+        if quoted {
+            return Err(ParseError::UnterminatedQuote(epd.to_owned()));
+        }
+        State::push_epd_operation(&mut operations, &operation)?;
         
-        State { pieces, to_move, half_moves, full_moves, config, previous_move: None }
+        let half_moves = operations.get("hmvc").map_or("0", |value| value.as_str());
+        let full_moves = operations.get("fmvn").map_or("1", |value| value.as_str());
+        let fen = format!("{} {} {}", board.join(" "), half_moves, full_moves);
+        Ok((State::from_fen(fen)?, operations))
+    }
+    
+    fn push_epd_operation(operations: &mut HashMap<String, String>, operation: &str) -> Result<(), ParseError> {
+        let operation = operation.trim();
+        if operation.is_empty() {
+            return Ok(());
+        }
+        let (opcode, operands) = operation.split_once(char::is_whitespace).unwrap_or((operation, ""));
+        let operands = operands.trim();
+        let operands = operands.strip_prefix('"').and_then(|value| value.strip_suffix('"')).unwrap_or(operands);
+        if operations.insert(opcode.to_owned(), operands.to_owned()).is_some() {
+            return Err(ParseError::DuplicateOpcode(opcode.to_owned()));
+        }
+        Ok(())
+    }
+    
+    // rebuilds the double pawn step that made the en-passant square available
+    fn en_passant_move(pieces: &[Piece], en_passant: &str, to_move: PieceColor) -> Result<Option<Move>, ParseError> {
+        if en_passant == "-" {
+            return Ok(None);
+        }
+        let square = match algebraic_to_position(en_passant) {
+            Some(square) => square,
+            None => return Err(ParseError::InvalidEnPassant(en_passant.to_owned())),
+        };
+        let pawn_color = to_move.opposite();
+        let start = square - pawn_color.forward();
+        let end = square + pawn_color.forward();
+        let pawn = pieces.iter().find(|piece| {
+            (piece.get_position() == &end) && (piece.get_piece_type() == PieceType::PAWN) && (piece.get_color() == pawn_color)
+        });
+        let mut pawn = match pawn {
+            Some(pawn) => pawn.clone(),
+            None => return Err(ParseError::NoEnPassantPawn(en_passant.to_owned())),
+        };
+        pawn.set_position(start);
+        Ok(Some(Move::new(start, end, pawn, None, None, false, None, false)))
+    }
+    
+    fn apply_castling_rights(pieces: &mut [Piece], castling: &str) -> Result<(), ParseError> {
+        if castling != "-" {
+            for (idx, symbol) in castling.char_indices() {
+                if !"KQkq".contains(symbol) {
+                    return Err(ParseError::InvalidCastling { symbol, castling: castling.to_owned() });
+                }
+                if castling[..idx].contains(symbol) {
+                    return Err(ParseError::DuplicateCastling { symbol, castling: castling.to_owned() });
+                }
+            }
+        }
+        
+        for color in [PieceColor::WHITE, PieceColor::BLACK] {
+            let (kingside, queenside) = match color {
+                PieceColor::WHITE => ('K', 'Q'),
+                PieceColor::BLACK => ('k', 'q'),
+            };
+            let kingside = castling.contains(kingside);
+            let queenside = castling.contains(queenside);
+            
+            let king_position = match pieces.iter().find(|piece| {
+                (piece.get_piece_type() == PieceType::KING) && (piece.get_color() == color)
+            }) {
+                Some(king) => *king.get_position(),
+                None => continue,
+            };
+            
+            for piece in pieces.iter_mut() {
+                if piece.get_color() != color {
+                    continue;
+                }
+                match piece.get_piece_type() {
+                    PieceType::KING if !kingside && !queenside => piece.moved(),
+                    PieceType::ROOK => {
+                        if piece.get_position().y != king_position.y {
+                            piece.moved();
+                            continue;
+                        }
+                        let allowed = match piece.get_position().x > king_position.x {
+                            true => kingside,
+                            false => queenside,
+                        };
+                        if !allowed {
+                            piece.moved();
+                        }
+                    },
+                    _ => {}
+                }
+            }
+        }
+        Ok(())
+    }
+    
+    // an infinite board has no edges to walk, only the box around its pieces is written out
+    pub fn to_fen(&self) -> String {
+        let (low, high) = self.bounding_box();
+        
+        let mut placement = String::new();
+        for y in (low.y..=high.y).rev() {
+            let mut empty = 0;
+            for x in low.x..=high.x {
+                match self.get_piece_at(Vec2::new(x, y)) {
+                    Some(piece) => {
+                        if empty > 0 {
+                            placement += &empty.to_string();
+                            empty = 0;
+                        }
+                        placement.push(self.config.pieces.symbol(piece));
+                    },
+                    None => empty += 1
+                }
+            }
+            if empty > 0 {
+                placement += &empty.to_string();
+            }
+            if y > low.y {
+                placement.push('/');
+            }
+        }
+        
+        let to_move = match self.to_move {
+            PieceColor::WHITE => "w",
+            PieceColor::BLACK => "b",
+        };
+        
+        let mut castling = String::new();
+        for color in [PieceColor::WHITE, PieceColor::BLACK] {
+            for king in self.find(PieceType::KING, color) {
+                if king.has_moved() {
+                    continue;
+                }
+                let mut sides = String::new();
+                for rook in self.find(PieceType::ROOK, color) {
+                    if rook.has_moved() || (rook.get_position().y != king.get_position().y) {
+                        continue;
+                    }
+                    if rook.get_position().x > king.get_position().x {
+                        sides.insert(0, 'K');
+                    }
+                    else {
+                        sides.push('Q');
+                    }
+                }
+                match color {
+                    PieceColor::WHITE => castling += &sides,
+                    PieceColor::BLACK => castling += &sides.to_lowercase(),
+                }
+            }
+        }
+        if castling.is_empty() {
+            castling.push('-');
+        }
+        
+        let en_passant = self.get_en_passant_target()
+            .and_then(position_to_algebraic)
+            .unwrap_or("-".to_owned());
+        
+        format!("{} {} {} {} {} {}", placement, to_move, castling, en_passant, self.half_moves, self.full_moves)
+    }
+    
+    pub fn get_en_passant_target(&self) -> Option<Vec2> {
+        self.en_passant_square
+    }
+    
+    // the side to move and the side waiting, so callers do not have to flip to_move themselves
+    pub fn us(&self) -> PieceColor {
+        self.to_move
+    }
+    
+    pub fn them(&self) -> PieceColor {
+        self.to_move.opposite()
+    }
+    
+    pub fn side_to_move_is_white(&self) -> bool {
+        self.to_move == PieceColor::WHITE
+    }
+    
+    // for setting up positions, handing the turn over drops the en passant square the other side left
+    pub fn with_side_to_move(mut self, color: PieceColor) -> State {
+        if color != self.to_move {
+            self.en_passant_square = None;
+        }
+        self.to_move = color;
+        self
+    }
+    
+    pub fn hash(&self) -> u64 {
+        // built once, every make_move hashes the position it leaves
+        static TABLE: OnceLock<ZobristTable> = OnceLock::new();
+        let table = TABLE.get_or_init(ZobristTable::default);
+        let mut hash = 0;
+        for piece in self.pieces.iter() {
+            if piece.is_alive() {
+                hash ^= table.piece_key(piece.get_piece_type(), piece.get_color(), *piece.get_position());
+            }
+        }
+        if self.to_move == PieceColor::BLACK {
+            hash ^= table.side_key();
+        }
+        if let Some(target) = self.get_en_passant_target() {
+            hash ^= table.en_passant_key(target.x);
+        }
+        for (color, rook) in self.castling_rights() {
+            hash ^= table.castling_key(color, rook);
+        }
+        hash
+    }
+    
+    // the rooks each side may still castle with: unmoved, beside an unmoved king of their color on its rank
+    pub fn castling_rights(&self) -> Vec<(PieceColor, Vec2)> {
+        let unmoved = |piece: &&Piece, piece_type: PieceType| piece.is_alive() && !piece.has_moved() && (piece.get_piece_type() == piece_type);
+        let mut rights: Vec<(PieceColor, Vec2)> = self.pieces.iter()
+            .filter(|rook| unmoved(rook, PieceType::ROOK))
+            .filter(|rook| self.pieces.iter().any(|king| {
+                unmoved(&king, PieceType::KING) && (king.get_color() == rook.get_color()) && (king.get_position().y == rook.get_position().y)
+            }))
+            .map(|rook| (rook.get_color(), *rook.get_position()))
+            .collect();
+        rights.sort_by_key(|(color, rook)| (*color as u8, rook.x, rook.y));
+        rights
+    }
+    
+    // alive pieces in a fixed order so the piece list order does not matter
+    fn placement(&self) -> Vec<(i32, i32, u8, usize)> {
+        let mut placement: Vec<(i32, i32, u8, usize)> = self.pieces.iter()
+            .filter(|piece| piece.is_alive())
+            .map(|piece| (piece.get_position().x, piece.get_position().y, piece.get_color() as u8, piece.get_piece_type().index()))
+            .collect();
+        placement.sort();
+        placement
     }
     
     pub fn get_pieces(&self) -> Vec<Piece> {
-        return self.pieces.clone();
+        self.pieces.clone()
+    }
+    
+    pub fn occupied_squares(&self) -> impl Iterator<Item = (Vec2, &Piece)> + '_ {
+        self.pieces.iter()
+            .filter(|piece| piece.is_alive())
+            .map(|piece| (*piece.get_position(), piece))
+    }
+    
+    // lowest and highest square, both inclusive. a board with edges gives its own corners,
+    // an infinite one the smallest box around its pieces
+    pub fn bounding_box(&self) -> (Vec2, Vec2) {
+        if self.config.topology != Topology::Infinite {
+            let [top_left, bottom_right] = self.config.boundaries;
+            return (Vec2::new(top_left.x + 1, bottom_right.y + 1), Vec2::new(bottom_right.x - 1, top_left.y - 1));
+        }
+        self.occupied_squares()
+            .map(|(square, _)| (square, square))
+            .reduce(|(low, high), (square, _)| (low.min(square), high.max(square)))
+            .unwrap_or((Vec2::ZERO, Vec2::ZERO))
     }
     
+    // the same position moved by delta, with its edges and promotion lines, so positions on an infinite
+    // board can be brought to one origin before comparing. a torus stays put and its squares wrap around.
+    // earlier moves cannot be taken back from the result, it starts its own history
+    pub fn translate(&self, delta: Vec2) -> Result<State, String> {
+        let delta = match self.config.topology {
+            Topology::Toroidal { width, height } => Vec2::new(delta.x.rem_euclid(width), delta.y.rem_euclid(height)),
+            _ => delta,
+        };
+        let shift = |square: Vec2| {
+            let moved = square.as_i64vec2() + delta.as_i64vec2();
+            let fits = moved.cmpge(I64Vec2::splat(i32::MIN as i64)).all() && moved.cmple(I64Vec2::splat(i32::MAX as i64)).all();
+            match fits {
+                true => Ok(self.wrap(moved.as_ivec2())),
+                false => Err(format!("Moving {} by {} leaves the coordinate range.", square, delta)),
+            }
+        };
+        let shift_piece = |piece: &Piece| -> Result<Piece, String> {
+            let mut piece = piece.clone();
+            piece.set_position(shift(*piece.get_position())?);
+            Ok(piece)
+        };
+        
+        let mut state = self.clone();
+        state.pieces = self.pieces.iter().map(shift_piece).collect::<Result<_, _>>()?;
+        state.en_passant_square = self.en_passant_square.map(shift).transpose()?;
+        state.previous_move = match &self.previous_move {
+            Some(previous) => Some(Move {
+                start: shift(previous.start)?,
+                end: shift(previous.end)?,
+                piece: shift_piece(&previous.piece)?,
+                target: previous.target.as_ref().map(shift_piece).transpose()?,
+                castling_target: previous.castling_target.as_ref().map(shift_piece).transpose()?,
+                ..previous.clone()
+            }),
+            None => None,
+        };
+        // an infinite board's boundaries are the ends of the coordinate range and stay there
+        if self.config.topology == Topology::Bounded {
+            let [top_left, bottom_right] = self.config.boundaries;
+            state.config.boundaries = [shift(top_left)?, shift(bottom_right)?];
+        }
+        state.config.promotion_lines = self.config.promotion_lines.iter()
+            .map(|&line| shift(Vec2::new(1, line)).map(|square| square.y))
+            .collect::<Result<_, _>>()?;
+        state.history = History::default();
+        state.start = None;
+        Ok(state.indexed())
+    }
+    
+    // captured pieces keep their square, so only live ones count
     pub fn get_piece_at(&self, pos: Vec2) -> Option<&Piece> {
+        if let Some(occupancy) = &self.occupancy {
+            return occupancy.get(&pos).map(|idx| &self.pieces[*idx]);
+        }
         for piece_idx in 0..self.pieces.len() {
             let piece_pos = self.pieces[piece_idx].get_position();
-            if (piece_pos.x == pos.x) && (piece_pos.y == pos.y) {
+            if (piece_pos.x == pos.x) && (piece_pos.y == pos.y) && self.pieces[piece_idx].is_alive() {
                 return Some(&self.pieces[piece_idx])
             }
         }
-        return None
+        None
     }
     
-    fn find_piece_idx(&self, piece: Piece) -> Option<usize> {
-        let mut same_idx: usize = usize::MAX;
-        for idx in 0..self.pieces.len() {
-            if self.pieces[idx] == piece {
-                same_idx = idx;
-                break;
-            }
-        }
-        if same_idx < self.pieces.len() {
-            return Some(same_idx);
+    pub fn set_royal_at(&mut self, pos: Vec2, royal: bool) -> bool {
+        match self.pieces.iter_mut().find(|piece| piece.is_alive() && (piece.get_position() == &pos)) {
+            Some(piece) => {
+                piece.set_royal(royal);
+                true
+            },
+            None => false
         }
-        None
+    }
+    
+    // by id, the piece also has to still be alive on the square the move expects
+    fn find_piece_idx(&self, piece: &Piece) -> Option<usize> {
+        self.pieces.iter().position(|other| {
+            (other.get_id() == piece.get_id()) && other.is_alive() && (other.get_position() == piece.get_position())
+        })
     }
     
     pub fn find(&self, piece_type: PieceType, piece_color: PieceColor) -> Vec<Piece> {
@@ -90,61 +876,883 @@ impl State {
                 result.push(piece.clone());
             }
         }
-        return result;
+        result
     }
     
-    fn switch_to_move(&self) -> PieceColor {
+    pub fn legal_moves_iter(&self) -> impl Iterator<Item = Move> + '_ {
+        let in_check = self.is_king_in_check(self.us());
+        self.pieces.iter()
+            .filter(|piece| piece.is_alive() && (piece.get_color() == self.us()))
+            .flat_map(move |piece| Generator::new(piece.clone(), self.clone()).with_check(in_check))
+            // rooks offer the same castling moves as their king, count them once
+            .filter(|piece_move| !piece_move.castling || (piece_move.piece.get_piece_type() == PieceType::KING))
+    }
+    
+    // sorted by the order on Move, legal_moves_iter gives them as the generators find them
+    pub fn get_legal_moves(&self) -> Vec<Move> {
+        let mut moves: Vec<Move> = self.legal_moves_iter().collect();
+        moves.sort();
+        moves
+    }
+    
+    // empty when the square holds no piece of the side to move
+    pub fn legal_moves_from(&self, from: Vec2) -> Vec<Move> {
+        match self.get_piece_at(from) {
+            Some(piece) if piece.get_color() == self.to_move => {
+                Generator::new(piece.clone(), self.clone())
+                    .filter(|piece_move| !piece_move.castling || (piece.get_piece_type() == PieceType::KING))
+                    .collect()
+            },
+            _ => vec![],
+        }
+    }
+    
+    pub fn is_move_legal(&self, from: Vec2, to: Vec2, promotion: Option<PieceType>) -> bool {
+        self.find_legal_move(from, to, promotion).is_some()
+    }
+    
+    // castling can be given as the king's landing square or, like the generator does, the rook's square.
+    // a move that ends on to wins over a castling that only lands there
+    fn find_legal_move(&self, from: Vec2, to: Vec2, promotion: Option<PieceType>) -> Option<Move> {
+        let moves = self.legal_moves_from(from);
+        let exact = moves.iter().find(|piece_move| (piece_move.end == to) && (piece_move.promotion == promotion));
+        exact.or_else(|| moves.iter().find(|piece_move| {
+            piece_move.castling && (self.castling_squares(piece_move.start, piece_move.end).0 == to)
+        })).cloned()
+    }
+    
+    // the legal move a uci string like e2e4 or e7e8q stands for, castling as the king's move to its landing
+    // or the rook's square. what kind of move it is comes from the board, not from the string
+    pub fn parse_uci(&self, uci: &str) -> Result<Move, String> {
+        let invalid = || format!("Invalid uci move: {}", uci);
+        // a square is its file letters followed by its rank digits
+        let square_end = |text: &str| -> Option<usize> {
+            let letters = text.find(|symbol: char| !symbol.is_ascii_lowercase())?;
+            Some(text[letters..].find(|symbol: char| !symbol.is_ascii_digit()).map_or(text.len(), |digits| letters + digits))
+        };
+        let split = square_end(uci).ok_or_else(invalid)?;
+        let (from, rest) = uci.split_at(split);
+        let split = square_end(rest).ok_or_else(invalid)?;
+        let (to, promotion) = rest.split_at(split);
+        
+        let from = algebraic_to_position(from).ok_or_else(invalid)?;
+        let to = algebraic_to_position(to).ok_or_else(invalid)?;
+        if let Some(square) = [from, to].into_iter().find(|square| !self.is_in_bounds(*square)) {
+            return Err(format!("Square {} of uci move {} is off the board.", square, uci));
+        }
+        let promotion = match promotion.chars().collect::<Vec<char>>()[..] {
+            [] => None,
+            [letter] => match self.config.pieces.from_letter(letter) {
+                PieceType::NULL => return Err(invalid()),
+                piece_type => Some(piece_type),
+            },
+            _ => return Err(invalid()),
+        };
+        self.find_legal_move(from, to, promotion).ok_or(format!("Move {} is not legal.", uci))
+    }
+    
+    pub fn apply_uci(&self, uci: &str) -> Result<State, String> {
+        let piece_move = self.parse_uci(uci)?;
+        self.clone().try_make_move(piece_move)
+    }
+    
+    // squares whose contents differ between the two states, each with what other has there
+    pub fn diff(&self, other: &State) -> Vec<(Vec2, Option<Piece>)> {
+        let mut squares: Vec<Vec2> = self.occupied_squares().chain(other.occupied_squares()).map(|(square, _)| square).collect();
+        squares.sort_by_key(|square| (square.x, square.y));
+        squares.dedup();
+        squares.into_iter()
+            .filter(|square| self.get_piece_at(*square) != other.get_piece_at(*square))
+            .map(|square| (square, other.get_piece_at(square).cloned()))
+            .collect()
+    }
+    
+    pub fn legal_move_count(&self) -> usize {
+        self.legal_moves_iter().count()
+    }
+    
+    // counted as if it were the piece's turn, so both sides can be measured from one state
+    pub fn mobility(&self, piece: &Piece) -> usize {
+        let state = self.clone().with_side_to_move(piece.get_color());
+        Generator::new(piece.clone(), state)
+            .filter(|piece_move| !piece_move.castling || (piece.get_piece_type() == PieceType::KING))
+            .count()
+    }
+    
+    // captures by most valuable victim then least valuable attacker, promotions, quiet moves
+    pub fn order_moves(&self, moves: &mut [Move]) {
+        moves.sort_by_key(|piece_move| {
+            let attacker = match piece_move.piece.get_piece_type() {
+                PieceType::KING => 1000,
+                piece_type => self.config.pieces.value(piece_type),
+            };
+            let victim = match &piece_move.target {
+                Some(target) if target.get_color() != piece_move.piece.get_color() => Some(self.config.pieces.value(target.get_piece_type())),
+                _ if piece_move.en_passant => Some(PieceType::PAWN.value()),
+                _ => None,
+            };
+            let score = match (victim, piece_move.promotion) {
+                (Some(victim), _) => 1_000_000 + victim * 10 - attacker,
+                (None, Some(promotion)) => 100_000 + self.config.pieces.value(promotion),
+                (None, None) => 0,
+            };
+            Reverse(score)
+        });
+    }
+    
+    pub(crate) fn is_in_bounds(&self, point: Vec2) -> bool {
+        if let Topology::Toroidal { .. } = self.config.topology {
+            return true;
+        }
+        let top_left = self.config.boundaries[0];
+        let bottom_right = self.config.boundaries[1];
+        (point.x < bottom_right.x) && (point.x > top_left.x)
+            && (point.y > bottom_right.y) && (point.y < top_left.y)
+    }
+    
+    pub(crate) fn wrap(&self, point: Vec2) -> Vec2 {
+        match self.config.topology {
+            Topology::Toroidal { width, height } => Vec2::new((point.x - 1).rem_euclid(width) + 1, (point.y - 1).rem_euclid(height) + 1),
+            _ => point,
+        }
+    }
+    
+    // on a torus the shortest way around is taken on each axis
+    pub fn displacement(&self, from: Vec2, to: Vec2) -> Vec2 {
+        let offset = to - from;
+        match self.config.topology {
+            Topology::Toroidal { width, height } => {
+                let shortest = |offset: i32, size: i32| {
+                    let offset = offset.rem_euclid(size);
+                    if offset * 2 > size { offset - size } else { offset }
+                };
+                Vec2::new(shortest(offset.x, width), shortest(offset.y, height))
+            },
+            _ => offset,
+        }
+    }
+    
+    // far apart squares on an unbounded board can be further than an i32 apart
+    fn wide_displacement(&self, from: Vec2, to: Vec2) -> I64Vec2 {
+        match self.config.topology {
+            Topology::Toroidal { .. } => self.displacement(from, to).as_i64vec2(),
+            _ => to.as_i64vec2() - from.as_i64vec2(),
+        }
+    }
+    
+    // walks until the first occupied square, which is kept only if it holds an enemy piece
+    pub fn ray_attacks(&self, from: Vec2, direction: Vec2) -> Vec<Vec2> {
+        let color = self.get_piece_at(from).map(|piece| piece.get_color());
+        let mut squares = vec![];
+        let mut square = self.wrap(from.saturating_add(direction));
+        // a ray around a torus ends when it comes back to where it started
+        while self.is_in_bounds(square) && (square != from) {
+            match self.get_piece_at(square) {
+                Some(piece) => {
+                    if Some(piece.get_color()) != color {
+                        squares.push(square);
+                    }
+                    break;
+                },
+                None => squares.push(square),
+            }
+            square = self.wrap(square.saturating_add(direction));
+        }
+        squares
+    }
+    
+    pub fn is_square_attacked(&self, target: Vec2, by_color: PieceColor) -> bool {
+        self.pieces.iter().any(|piece| {
+            piece.is_alive() && (piece.get_color() == by_color) && self.attacks(piece, target)
+        })
+    }
+    
+    // only pays off after a few hundred queries on the same position, single checks are cheaper directly.
+    // the map is a snapshot, build a new one after a move
+    pub fn attack_map(&self, color: PieceColor) -> AttackMap {
+        AttackMap::new(self, color)
+    }
+    
+    // how many of color's pieces attack each square, unattacked ones included with 0. a board with edges
+    // is covered whole, an infinite one as far as the box around its pieces grown by color's longest leap
+    pub fn control_map(&self, color: PieceColor) -> HashMap<Vec2, u32> {
+        let map = self.attack_map(color);
+        let (low, high) = self.bounding_box();
+        let (low, high) = match self.config.topology {
+            Topology::Infinite => {
+                let reach = self.occupied_squares()
+                    .filter(|(_, piece)| piece.get_color() == color)
+                    .flat_map(|(_, piece)| self.config.pieces.leaper_offsets(piece.get_piece_type(), color))
+                    .map(|leap| leap.abs().max_element())
+                    .fold(1, i32::max);
+                (low.saturating_sub(Vec2::splat(reach)), high.saturating_add(Vec2::splat(reach)))
+            },
+            _ => (low, high),
+        };
+        (low.x..=high.x)
+            .flat_map(|x| (low.y..=high.y).map(move |y| Vec2::new(x, y)))
+            .map(|square| (square, map.attackers(square).len() as u32))
+            .collect()
+    }
+    
+    pub fn is_king_in_check(&self, color: PieceColor) -> bool {
+        self.get_royal_pieces(color).iter().any(|royal| {
+            self.is_square_attacked(*royal.get_position(), color.opposite())
+        })
+    }
+    
+    // squares of the enemy pieces attacking any royal piece of color
+    pub fn checkers(&self, color: PieceColor) -> Vec<Vec2> {
+        let royals = self.get_royal_pieces(color);
+        self.pieces.iter()
+            .filter(|piece| piece.is_alive() && (piece.get_color() != color))
+            .filter(|piece| royals.iter().any(|royal| self.attacks(piece, *royal.get_position())))
+            .map(|piece| *piece.get_position())
+            .collect()
+    }
+    
+    // squares of the pieces of color attacking square
+    pub fn attackers(&self, square: Vec2, color: PieceColor) -> Vec<Vec2> {
+        self.pieces.iter()
+            .filter(|piece| piece.is_alive() && (piece.get_color() == color) && self.attacks(piece, square))
+            .map(|piece| *piece.get_position())
+            .collect()
+    }
+    
+    // material color comes out with when it starts capturing on target and both sides keep
+    // recapturing with their cheapest attacker, either side may stop once it would lose by going on.
+    // pieces are taken off as they capture, so sliders lined up behind them join in
+    pub fn see(&self, target: Vec2, color: PieceColor) -> i32 {
+        // the king can only capture last, nothing may be left to take it back
+        let worth = |piece_type: PieceType| match piece_type {
+            PieceType::KING => 100_000,
+            piece_type => self.config.pieces.value(piece_type),
+        };
+        let mut board = self.clone();
+        let mut gains = vec![board.get_piece_at(target).map_or(0, |piece| self.config.pieces.value(piece.get_piece_type()))];
+        let mut side = color;
+        loop {
+            let attacker = board.pieces.iter()
+                .enumerate()
+                .filter(|(_, piece)| piece.is_alive() && (piece.get_color() == side) && board.attacks(piece, target))
+                .min_by_key(|(_, piece)| worth(piece.get_piece_type()))
+                .map(|(idx, _)| idx);
+            let Some(attacker) = attacker else { break };
+            let gain = worth(board.pieces[attacker].get_piece_type()) - gains[gains.len() - 1];
+            gains.push(gain);
+            if let Some(victim) = board.pieces.iter().position(|piece| piece.is_alive() && (piece.get_position() == &target)) {
+                board.remove(victim);
+            }
+            board.relocate(attacker, target);
+            side = side.opposite();
+        }
+        // the last capture is only made if it pays, then walk back up the sequence
+        gains.pop();
+        while gains.len() > 1 {
+            let last = gains.pop().unwrap();
+            let previous = gains.len() - 1;
+            gains[previous] = -(-gains[previous]).max(last);
+        }
+        gains.first().copied().unwrap_or(0)
+    }
+    
+    // with two checkers only the king can get out of check
+    pub fn is_double_check(&self, color: PieceColor) -> bool {
+        self.checkers(color).len() > 1
+    }
+    
+    // how often the position has been on the board since the last irreversible move, this time included
+    pub fn repetition_count(&self) -> usize {
+        let hash = self.hash();
+        1 + self.history.iter()
+            .take_while(|played| !played.irreversible)
+            .filter(|played| played.hash == hash)
+            .count()
+    }
+    
+    // positions are told apart by their hash, which covers the placement, the side to move, castling rights and the en passant square
+    pub fn is_threefold_repetition(&self) -> bool {
+        self.repetition_count() >= 3
+    }
+    
+    pub fn is_checkmate(&self) -> bool {
+        self.is_king_in_check(self.us()) && self.legal_moves_iter().next().is_none()
+    }
+    
+    pub fn is_stalemate(&self) -> bool {
+        !self.is_king_in_check(self.us()) && self.legal_moves_iter().next().is_none()
+    }
+    
+    // a declared result, from a resignation or agreed draw, wins over the board
+    pub fn result(&self) -> GameResult {
+        if let Some(result) = self.declared_result {
+            return result;
+        }
+        if self.legal_moves_iter().next().is_some() {
+            if self.is_threefold_repetition() {
+                return GameResult::Draw;
+            }
+            return GameResult::Ongoing;
+        }
+        if !self.is_king_in_check(self.us()) {
+            return GameResult::Draw;
+        }
         match self.to_move {
-            PieceColor::BLACK => PieceColor::WHITE,
-            PieceColor::WHITE => PieceColor::BLACK,
+            PieceColor::WHITE => GameResult::BlackWins,
+            PieceColor::BLACK => GameResult::WhiteWins,
+        }
+    }
+    
+    pub fn is_game_over(&self) -> bool {
+        self.result() != GameResult::Ongoing
+    }
+    
+    pub fn with_capture_mode(mut self, capture_mode: CaptureMode) -> State {
+        self.capture_mode = capture_mode;
+        if capture_mode == CaptureMode::Compact {
+            self.compact();
+        }
+        self
+    }
+    
+    pub fn get_capture_mode(&self) -> CaptureMode {
+        self.capture_mode
+    }
+    
+    // drops the captured pieces for good, taking back a capture made before this fails afterwards
+    pub fn compact(&mut self) {
+        if self.pieces.iter().all(|piece| piece.is_alive()) {
+            return;
+        }
+        self.pieces.retain(|piece| piece.is_alive());
+        self.reindex();
+    }
+    
+    // declaring the game ongoing takes a declared result back and leaves it to the board again
+    pub fn with_result(mut self, result: GameResult) -> State {
+        self.declared_result = (result != GameResult::Ongoing).then_some(result);
+        self
+    }
+    
+    pub fn resign(self, color: PieceColor) -> State {
+        match color {
+            PieceColor::WHITE => self.with_result(GameResult::BlackWins),
+            PieceColor::BLACK => self.with_result(GameResult::WhiteWins),
         }
     }
     
+    pub fn get_royal_pieces(&self, color: PieceColor) -> Vec<Piece> {
+        self.pieces.iter()
+            .filter(|piece| piece.is_alive() && piece.is_royal() && (piece.get_color() == color))
+            .cloned()
+            .collect()
+    }
+    
+    // each pinned piece with the direction from it toward the royal piece it shields
+    pub fn pinned_pieces(&self, color: PieceColor) -> Vec<(Vec2, Vec2)> {
+        let mut pinned = vec![];
+        for royal in self.get_royal_pieces(color) {
+            for x in -1..=1 {
+                for y in -1..=1 {
+                    let direction = Vec2::new(x, y);
+                    if direction == Vec2::ZERO {
+                        continue;
+                    }
+                    if let Some(square) = self.pinned_on_ray(*royal.get_position(), direction, color) {
+                        pinned.push((square, -direction));
+                    }
+                }
+            }
+        }
+        pinned
+    }
+    
+    // custom pieces of color riding anything but single steps along straight or diagonal lines. pin_directions
+    // only follows those, a ride that skips squares like (2, 0) can pin where it does not look
+    pub(crate) fn rides_off_lines(&self, color: PieceColor) -> bool {
+        self.pieces.iter()
+            .filter(|piece| piece.is_alive() && (piece.get_color() == color) && matches!(piece.get_piece_type(), PieceType::CUSTOM(_)))
+            .any(|piece| self.config.pieces.get(piece.get_piece_type()).is_some_and(|custom| custom.rider_offsets.iter().any(|ride| ride.abs().max_element() != 1)))
+    }
+    
+    // same as pinned_pieces but only looks at the rays running through one square
+    pub(crate) fn pin_directions(&self, square: Vec2) -> Vec<Vec2> {
+        let color = match self.get_piece_at(square) {
+            Some(piece) => piece.get_color(),
+            None => return vec![],
+        };
+        self.get_royal_pieces(color).iter()
+            .filter_map(|royal| {
+                let offset = square.as_i64vec2() - royal.get_position().as_i64vec2();
+                let distance = offset.abs().max_element();
+                if (distance == 0) || ((offset.x != 0) && (offset.y != 0) && (offset.x.abs() != offset.y.abs())) {
+                    return None;
+                }
+                let direction = (offset / distance).as_ivec2();
+                (self.pinned_on_ray(*royal.get_position(), direction, color) == Some(square)).then_some(-direction)
+            })
+            .collect()
+    }
+    
+    fn pinned_on_ray(&self, origin: Vec2, direction: Vec2, color: PieceColor) -> Option<Vec2> {
+        // pieces on the ray ordered by distance, the board may be unbounded so no walking
+        let mut on_ray: Vec<(i64, &Piece)> = self.pieces.iter()
+            .filter(|piece| piece.is_alive())
+            .filter_map(|piece| {
+                let relative = piece.get_position().as_i64vec2() - origin.as_i64vec2();
+                let distance = relative.abs().max_element();
+                ((distance > 0) && (direction.as_i64vec2() * distance == relative)).then_some((distance, piece))
+            })
+            .collect();
+        on_ray.sort_by_key(|(distance, _)| *distance);
+        let [(_, shield), (_, attacker), ..] = on_ray[..] else {
+            return None;
+        };
+        let slider = match attacker.get_piece_type() {
+            PieceType::QUEEN => true,
+            PieceType::ROOK => (direction.x == 0) || (direction.y == 0),
+            PieceType::BISHOP => (direction.x != 0) && (direction.y != 0),
+            piece_type @ PieceType::CUSTOM(_) => self.config.pieces.rider_offsets(piece_type, attacker.get_color()).contains(&-direction),
+            _ => false,
+        };
+        ((shield.get_color() == color) && (attacker.get_color() != color) && slider).then_some(*shield.get_position())
+    }
+    
+    fn attacks(&self, piece: &Piece, target: Vec2) -> bool {
+        let offset = self.wide_displacement(*piece.get_position(), target);
+        if offset == I64Vec2::ZERO {
+            return false;
+        }
+        let toroidal = matches!(self.config.topology, Topology::Toroidal { .. });
+        if toroidal && piece.get_piece_type().is_sliding() {
+            return piece.get_piece_type().base_offsets(piece.get_color()).into_iter()
+                .any(|direction| self.wrapped_ray_reaches(*piece.get_position(), direction, target));
+        }
+        let straight = (offset.x == 0) || (offset.y == 0);
+        let diagonal = offset.x.abs() == offset.y.abs();
+        match piece.get_piece_type() {
+            PieceType::NULL => false,
+            PieceType::PAWN => (offset.y == piece.get_color().forward().y as i64) && (offset.x.abs() == 1),
+            PieceType::KNIGHT => {
+                let offset = offset.abs();
+                ((offset.x == 1) && (offset.y == 2)) || ((offset.x == 2) && (offset.y == 1))
+            },
+            PieceType::BISHOP => diagonal && self.is_path_clear(*piece.get_position(), target),
+            PieceType::ROOK => straight && self.is_path_clear(*piece.get_position(), target),
+            PieceType::QUEEN => (straight || diagonal) && self.is_path_clear(*piece.get_position(), target),
+            PieceType::KING => offset.abs().max_element() == 1,
+            piece_type => {
+                let (position, color) = (*piece.get_position(), piece.get_color());
+                self.config.pieces.leaper_offsets(piece_type, color).into_iter().any(|step| self.wrap(position.saturating_add(step)) == target)
+                    || self.config.pieces.rider_offsets(piece_type, color).into_iter().any(|direction| self.rider_reaches(position, direction, target))
+            },
+        }
+    }
+    
+    // a ride may skip squares, the target has to be a whole number of steps away with nothing on the ones in between
+    fn rider_reaches(&self, from: Vec2, direction: Vec2, target: Vec2) -> bool {
+        if matches!(self.config.topology, Topology::Toroidal { .. }) {
+            return self.wrapped_ray_reaches(from, direction, target);
+        }
+        let direction = direction.as_i64vec2();
+        let offset = target.as_i64vec2() - from.as_i64vec2();
+        let steps = |relative: I64Vec2| match direction.x {
+            0 => relative.y / direction.y,
+            _ => relative.x / direction.x,
+        };
+        let distance = steps(offset);
+        if (distance < 1) || (direction * distance != offset) {
+            return false;
+        }
+        !self.pieces.iter().any(|piece| {
+            let relative = piece.get_position().as_i64vec2() - from.as_i64vec2();
+            let between = steps(relative);
+            piece.is_alive() && (between > 0) && (between < distance) && (direction * between == relative)
+        })
+    }
+    
+    // lines wrap on a torus so there the squares have to be walked
+    fn wrapped_ray_reaches(&self, from: Vec2, direction: Vec2, target: Vec2) -> bool {
+        let mut square = self.wrap(from + direction);
+        while square != from {
+            if square == target {
+                return true;
+            }
+            if self.get_piece_at(square).is_some() {
+                return false;
+            }
+            square = self.wrap(square + direction);
+        }
+        false
+    }
+    
+    // checks pieces against the line instead of walking it, distances can be arbitrarily long
+    fn is_path_clear(&self, start: Vec2, end: Vec2) -> bool {
+        let offset = end.as_i64vec2() - start.as_i64vec2();
+        let steps = offset.abs().max_element();
+        let direction = offset / steps;
+        self.pieces.iter().all(|piece| {
+            if !piece.is_alive() {
+                return true;
+            }
+            let relative = piece.get_position().as_i64vec2() - start.as_i64vec2();
+            let distance = relative.abs().max_element();
+            !((distance > 0) && (distance < steps) && (direction * distance == relative))
+        })
+    }
+    
+    // on 8 file boards the king lands on the c or g file like in standard chess and chess960,
+    // elsewhere it goes two squares towards the rook, the rook always ends beside it on the inner side
+    pub(crate) fn castling_squares(&self, king: Vec2, rook: Vec2) -> (Vec2, Vec2) {
+        let direction = Vec2::new(rook.x.cmp(&king.x) as i32, 0);
+        let left = self.config.boundaries[0].x;
+        let king_end = match self.config.boundaries[1].x.checked_sub(left) {
+            Some(9) if direction.x > 0 => Vec2::new(left + 7, king.y),
+            Some(9) => Vec2::new(left + 3, king.y),
+            _ => king.saturating_add(direction * 2),
+        };
+        (king_end, king_end - direction)
+    }
+    
+    // for moves coming from the generator, anything else should go through try_make_move
     pub fn make_move(self, next_move: Move) -> State {
+        self.make_move_unchecked(next_move).unwrap_or_else(|error| panic!("{}", error))
+    }
+    
+    // make_move for looking ahead, the new position starts without a history and cannot be taken back
+    pub fn simulate_move(self, next_move: Move) -> State {
+        self.play(next_move, false).unwrap_or_else(|error| panic!("{}", error))
+    }
+    
+    // a stale move or one for the wrong side would leave the board in a state no game can reach
+    pub fn try_make_move(self, next_move: Move) -> Result<State, String> {
+        if next_move.piece.get_color() != self.us() {
+            return Err(format!("It is {:?}'s turn, not {:?}'s.", self.us(), next_move.piece.get_color()));
+        }
+        let on_start = self.get_piece_at(next_move.start).is_some_and(|piece| piece == &next_move.piece);
+        if !on_start || (next_move.start != *next_move.piece.get_position()) {
+            return Err(format!("The moving piece is not on {}.", next_move.start));
+        }
+        self.make_move_unchecked(next_move)
+    }
+    
+    // skips the checks of try_make_move, the search only ever feeds it moves the generator made
+    pub fn make_move_unchecked(self, next_move: Move) -> Result<State, String> {
+        self.play(next_move, true)
+    }
+    
+    fn play(self, next_move: Move, record: bool) -> Result<State, String> {
         let pieces = self.pieces.clone();
-        let to_move: PieceColor = self.switch_to_move();
-        let half_moves: usize = self.half_moves + 1;
+        let to_move: PieceColor = self.them();
+        // pawn moves and captures reset the fifty move clock, castling targets the own rook
+        let capture = next_move.en_passant || next_move.target.as_ref().is_some_and(|target| {
+            target.is_alive() && (target.get_color() != next_move.piece.get_color())
+        });
+        let half_moves: usize = match capture || (next_move.piece.get_piece_type() == PieceType::PAWN) {
+            true => 0,
+            false => self.half_moves + 1,
+        };
         let full_moves: usize = self.full_moves + match to_move {
             PieceColor::WHITE => 1,
             PieceColor::BLACK => 0,
         };
+        let step = self.displacement(next_move.start, next_move.end);
+        let en_passant_square = match (next_move.piece.get_piece_type() == PieceType::PAWN) && (step.y.abs() == 2) {
+            true => Some(self.wrap(next_move.start + step / 2)),
+            false => None,
+        };
+        // only needed for counting repetitions and exports, simulated moves are never looked back at
+        let (hash, castling_rights, start) = match record {
+            true => (self.hash(), self.castling_rights(), Some(self.start.clone().unwrap_or_else(|| Arc::new(self.clone())))),
+            false => (0, vec![], None),
+        };
+        let mut played = PlayedMove {
+            piece_move: Some(next_move.clone()),
+            captured: None,
+            to_move: self.to_move,
+            half_moves: self.half_moves,
+            full_moves: self.full_moves,
+            previous_move: self.previous_move,
+            en_passant_square: self.en_passant_square,
+            hash,
+            irreversible: (half_moves == 0) || next_move.castling,
+        };
         let config = self.config;
         let previous_move = Some(next_move.clone());
+        let history = match record {
+            true => self.history.clone(),
+            false => History::default(),
+        };
+        let declared_result = self.declared_result;
+        let capture_mode = self.capture_mode;
+        let occupancy = self.occupancy;
         
-        let mut state = State { pieces, to_move, half_moves, full_moves, config, previous_move};
+        let mut state = State { pieces, to_move, half_moves, full_moves, config, previous_move, en_passant_square, history, start, declared_result, capture_mode, occupancy };
         
         if !next_move.castling {
-            let idx = state.find_piece_idx(next_move.piece).expect("Piece does not exist.");
-            state.pieces[idx].set_position(next_move.end);
+            let mover_color = next_move.piece.get_color();
+            let idx = state.find_piece_idx(&next_move.piece).ok_or("Piece does not exist.")?;
+            state.relocate(idx, next_move.end);
+            state.pieces[idx].moved();
+            // the generator takes its offsets from the piece type, so the new piece moves as such right away
+            if let Some(promotion) = next_move.promotion {
+                if next_move.piece.get_piece_type() != PieceType::PAWN {
+                    return Err("Only pawns can promote.".to_owned());
+                }
+                if !state.config.allows_promotion(promotion) {
+                    return Err(format!("Cannot promote to {}.", state.config.pieces.name(promotion)));
+                }
+                state.pieces[idx].promote(promotion);
+            }
             
-            if !next_move.target.is_none() {
-                let idx = state.find_piece_idx(next_move.target.unwrap()).unwrap();
-                state.pieces[idx].capture();
+            if let Some(target) = next_move.target.filter(|target| target.is_alive()) {
+                let idx = state.find_piece_idx(&target).ok_or("Captured piece does not exist.")?;
+                state.remove(idx);
+                played.captured = Some(target.get_id());
+            }
+            
+            // captured pawn sits beside the start square, not on the end square
+            if next_move.en_passant {
+                let captured = Vec2::new(next_move.end.x, next_move.start.y);
+                let idx = state.pieces.iter().position(|piece| {
+                    piece.is_alive() && (piece.get_position() == &captured) && (piece.get_piece_type() == PieceType::PAWN) && (piece.get_color() != mover_color)
+                }).ok_or("No pawn to capture en passant.")?;
+                state.remove(idx);
+                played.captured = Some(state.pieces[idx].get_id());
+            }
+        }
+        else {
+            let castling_target = next_move.castling_target.ok_or("Castling move without a castling target.")?;
+            let (king, rook) = match next_move.piece.get_piece_type() {
+                PieceType::KING => (next_move.piece, castling_target),
+                _ => (castling_target, next_move.piece),
+            };
+            let king_idx = state.find_piece_idx(&king).ok_or("King does not exist.")?;
+            let rook_idx = state.find_piece_idx(&rook).ok_or("Rook does not exist.")?;
+            
+            let (king_end, rook_end) = state.castling_squares(*king.get_position(), *rook.get_position());
+            
+            state.relocate(king_idx, king_end);
+            state.pieces[king_idx].moved();
+            state.relocate(rook_idx, rook_end);
+            state.pieces[rook_idx].moved();
+        }
+        
+        if played.captured.is_some() && (state.capture_mode == CaptureMode::Compact) {
+            state.compact();
+        }
+        
+        if record {
+            played.irreversible |= state.castling_rights() != castling_rights;
+            state.history = state.history.push(played);
+        }
+        Ok(state)
+    }
+    
+    // passes the turn for null move pruning, not allowed while in check
+    pub fn make_null_move(&self) -> Result<State, String> {
+        if self.is_king_in_check(self.us()) {
+            return Err("Cannot pass while in check.".to_owned());
+        }
+        let mut state = self.clone();
+        state.start = Some(self.start.clone().unwrap_or_else(|| Arc::new(self.clone())));
+        state.history = self.history.push(PlayedMove {
+            piece_move: None,
+            captured: None,
+            to_move: self.to_move,
+            half_moves: self.half_moves,
+            full_moves: self.full_moves,
+            previous_move: self.previous_move.clone(),
+            en_passant_square: self.en_passant_square,
+            hash: self.hash(),
+            irreversible: false,
+        });
+        state.to_move = self.them();
+        state.half_moves += 1;
+        if state.to_move == PieceColor::WHITE {
+            state.full_moves += 1;
+        }
+        state.previous_move = None;
+        state.en_passant_square = None;
+        Ok(state)
+    }
+    
+    // takes back the last move and returns it, None if the turn was passed. the moved pieces come back as they
+    // were before it and a captured one is revived. castling rights come back with the king and rook, the rest
+    // is copied from the entry. on an error nothing has changed
+    pub fn unmake_move(&mut self) -> Result<Option<Move>, String> {
+        let (played, earlier) = self.history.split_last().ok_or("No move to take back.")?;
+        let mut before = vec![];
+        if let Some(piece_move) = &played.piece_move {
+            before.push(&piece_move.piece);
+            if piece_move.castling {
+                before.extend(piece_move.castling_target.as_ref());
             }
         }
-        if next_move.castling {
-            todo!()
+        let restored = before.into_iter()
+            .map(|piece| self.piece_idx_by_id(piece.get_id()).map(|idx| (idx, piece.clone())).ok_or("Moved piece does not exist."))
+            .collect::<Result<Vec<(usize, Piece)>, _>>()?;
+        let captured = match played.captured {
+            Some(id) => Some(self.piece_idx_by_id(id).ok_or("Captured piece does not exist.")?),
+            None => None,
+        };
+        
+        let played = played.clone();
+        self.history = earlier.clone();
+        if self.history.split_last().is_none() {
+            self.start = None;
+        }
+        for (idx, piece) in restored {
+            self.pieces[idx] = piece;
+        }
+        if let Some(idx) = captured {
+            self.pieces[idx].revive();
+        }
+        self.to_move = played.to_move;
+        self.half_moves = played.half_moves;
+        self.full_moves = played.full_moves;
+        self.previous_move = played.previous_move;
+        self.en_passant_square = played.en_passant_square;
+        self.reindex();
+        Ok(played.piece_move)
+    }
+    
+    fn indexed(mut self) -> State {
+        self.reindex();
+        self
+    }
+    
+    fn reindex(&mut self) {
+        let occupancy = self.pieces.iter()
+            .enumerate()
+            .filter(|(_, piece)| piece.is_alive())
+            .map(|(idx, piece)| (*piece.get_position(), idx))
+            .collect();
+        self.occupancy = Some(occupancy);
+    }
+    
+    // updates the index in place instead of rebuilding it, a square another piece has taken over meanwhile stays theirs
+    fn relocate(&mut self, idx: usize, to: Vec2) {
+        let from = *self.pieces[idx].get_position();
+        if let Some(occupancy) = &mut self.occupancy {
+            if occupancy.get(&from) == Some(&idx) {
+                occupancy.remove(&from);
+            }
+            occupancy.insert(to, idx);
         }
+        self.pieces[idx].set_position(to);
+    }
+    
+    fn remove(&mut self, idx: usize) {
+        let square = *self.pieces[idx].get_position();
+        if let Some(occupancy) = &mut self.occupancy {
+            if occupancy.get(&square) == Some(&idx) {
+                occupancy.remove(&square);
+            }
+        }
+        self.pieces[idx].capture();
+    }
+    
+    fn piece_idx_by_id(&self, id: u32) -> Option<usize> {
+        self.pieces.iter().position(|piece| piece.get_id() == id)
+    }
+    
+}
+
+// equal states are the same position, not the same game: move counters and history are ignored.
+// castling rights count, like in the repetition rule
+impl PartialEq for State {
+    fn eq(&self, other: &State) -> bool {
+        (self.to_move == other.to_move)
+            && (self.get_en_passant_target() == other.get_en_passant_target())
+            && (self.placement() == other.placement())
+            && (self.castling_rights() == other.castling_rights())
+    }
+}
+
+impl Eq for State {}
+
+impl Hash for State {
+    fn hash<H: Hasher>(&self, hasher: &mut H) {
+        hasher.write_u64(State::hash(self));
+    }
+}
+
+impl Default for State {
+    fn default() -> State {
+        State::try_default().unwrap_or_else(|error| panic!("Invalid start position: {}", error))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct StateBuilder {
+    pieces: Vec<Piece>,
+    to_move: PieceColor,
+    config: Config,
+    allow_no_king: bool,
+}
 
-        state
+impl StateBuilder {
+    pub fn new() -> StateBuilder {
+        StateBuilder { pieces: vec![], to_move: PieceColor::WHITE, config: Config::default(), allow_no_king: false }
+    }
+    
+    pub fn place(mut self, piece_type: PieceType, piece_color: PieceColor, position: Vec2) -> StateBuilder {
+        let mut piece = Piece::new(piece_color, piece_type, position);
+        piece.set_id(self.pieces.len() as u32);
+        self.pieces.push(piece);
+        self
     }
     
+    pub fn to_move(mut self, to_move: PieceColor) -> StateBuilder {
+        self.to_move = to_move;
+        self
+    }
+    
+    pub fn config(mut self, config: Config) -> StateBuilder {
+        self.config = config;
+        self
+    }
+    
+    pub fn allow_no_king(mut self) -> StateBuilder {
+        self.allow_no_king = true;
+        self
+    }
+    
+    pub fn build(self) -> Result<State, String> {
+        for (idx, piece) in self.pieces.iter().enumerate() {
+            if self.pieces[..idx].iter().any(|other| other.get_position() == piece.get_position()) {
+                return Err(format!("Square {} is occupied twice.", piece.get_position()));
+            }
+        }
+        if !self.allow_no_king {
+            for color in [PieceColor::WHITE, PieceColor::BLACK] {
+                if !self.pieces.iter().any(|piece| (piece.get_piece_type() == PieceType::KING) && (piece.get_color() == color)) {
+                    return Err(format!("No {:?} king placed.", color));
+                }
+            }
+        }
+        Ok(State::indexed(State { pieces: self.pieces, to_move: self.to_move, half_moves: 0, full_moves: 0, config: self.config, previous_move: None, en_passant_square: None, history: History::default(), start: None, declared_result: None, capture_mode: CaptureMode::default(), occupancy: None }))
+    }
+}
+
+impl Default for StateBuilder {
+    fn default() -> StateBuilder {
+        StateBuilder::new()
+    }
 }
 
 impl fmt::Display for State {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            let (low, high) = self.bounding_box();
+            let occupied: HashMap<Vec2, &Piece> = self.occupied_squares().collect();
             let mut result = String::new();
-            for y in (1..9).rev() {
-                for x in 1..9 {
-                    let piece = self.get_piece_at(Vec2::new(x, y));
-                    let piece: &Piece = match piece {
-                        Some(_) => piece.unwrap(),
-                        None => {
-                            result += ".";
-                            continue
-                        }
-                    };
-                    result += piece.get_symbol().to_string().as_str();
+            for y in (low.y..=high.y).rev() {
+                for x in low.x..=high.x {
+                    match occupied.get(&Vec2::new(x, y)) {
+                        Some(piece) => result.push(self.config.pieces.symbol(piece)),
+                        None => result.push('.'),
+                    }
                 }
                 result += "\n";
             }
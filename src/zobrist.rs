@@ -0,0 +1,73 @@
+use glam::IVec2 as Vec2;
+
+use crate::pieces::{PieceColor, PieceType};
+
+const SEED: u64 = 0x51_7C_C1_B7_27_22_0A_95;
+
+fn splitmix64(value: u64) -> u64 {
+    let mut z = value.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+#[derive(Debug, Clone)]
+pub struct ZobristTable {
+    pieces: [[u64; 7]; 2],
+    side: u64,
+    en_passant: u64,
+    castling: u64,
+}
+
+impl ZobristTable {
+    pub fn new(seed: u64) -> ZobristTable {
+        let mut state = seed;
+        let mut next = || {
+            state = splitmix64(state);
+            state
+        };
+
+        let mut pieces = [[0; 7]; 2];
+        for color in pieces.iter_mut() {
+            for key in color.iter_mut() {
+                *key = next();
+            }
+        }
+        let side = next();
+        let en_passant = next();
+        let castling = next();
+        ZobristTable { pieces, side, en_passant, castling }
+    }
+
+    // the board is unbounded, so squares are mixed into the piece key instead of indexing a table
+    pub fn piece_key(&self, piece_type: PieceType, piece_color: PieceColor, square: Vec2) -> u64 {
+        let square = (square.x as u32 as u64) | ((square.y as u32 as u64) << 32);
+        let keys = &self.pieces[piece_color as usize];
+        // custom pieces have no slot, their key is derived from the null piece's
+        let key = match keys.get(piece_type.index()) {
+            Some(key) => *key,
+            None => splitmix64(keys[0] ^ piece_type.index() as u64),
+        };
+        splitmix64(key ^ splitmix64(square))
+    }
+
+    pub fn side_key(&self) -> u64 {
+        self.side
+    }
+
+    pub fn en_passant_key(&self, file: i32) -> u64 {
+        splitmix64(self.en_passant ^ file as u32 as u64)
+    }
+
+    // one key per rook a side may still castle with, chess960 and wide boards can have them anywhere
+    pub fn castling_key(&self, piece_color: PieceColor, rook: Vec2) -> u64 {
+        let square = (rook.x as u32 as u64) | ((rook.y as u32 as u64) << 32);
+        splitmix64(self.castling ^ splitmix64(square ^ piece_color as u64))
+    }
+}
+
+impl Default for ZobristTable {
+    fn default() -> ZobristTable {
+        ZobristTable::new(SEED)
+    }
+}
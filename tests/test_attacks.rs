@@ -0,0 +1,102 @@
+use quasar::config::Config;
+use quasar::pieces::{CustomPiece, PieceColor, PieceType};
+use quasar::state::*;
+use glam::IVec2 as Vec2;
+
+fn assert_matches_state(state: &State, squares: &[Vec2]) {
+    for color in [PieceColor::WHITE, PieceColor::BLACK] {
+        let map = state.attack_map(color);
+        for square in squares {
+            assert_eq!(map.is_attacked(*square), state.is_square_attacked(*square, color), "{:?} {}", color, square);
+        }
+    }
+}
+
+#[test]
+fn test_attack_map_matches_state() {
+    let board: Vec<Vec2> = (1..9).flat_map(|x| (1..9).map(move |y| Vec2::new(x, y))).collect();
+    let kiwipete = State::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1".to_owned()).unwrap();
+    assert_matches_state(&kiwipete, &board);
+    assert_matches_state(&State::default(), &board);
+
+    let torus = StateBuilder::new()
+        .config(Config::toroidal(8, 8, vec![]))
+        .place(PieceType::ROOK, PieceColor::WHITE, Vec2::new(1, 4))
+        .place(PieceType::KNIGHT, PieceColor::WHITE, Vec2::new(8, 1))
+        .place(PieceType::KING, PieceColor::WHITE, Vec2::new(3, 7))
+        .place(PieceType::KING, PieceColor::BLACK, Vec2::new(6, 2))
+        .build()
+        .unwrap();
+    assert_matches_state(&torus, &board);
+}
+
+#[test]
+fn test_attack_map_on_unbounded_board() {
+    let state = StateBuilder::new()
+        .place(PieceType::KING, PieceColor::WHITE, Vec2::ZERO)
+        .place(PieceType::KING, PieceColor::BLACK, Vec2::new(5, 5))
+        .place(PieceType::QUEEN, PieceColor::WHITE, Vec2::new(2, -3))
+        .place(PieceType::BISHOP, PieceColor::BLACK, Vec2::new(-4, 1))
+        .build()
+        .unwrap();
+    let far = [
+        Vec2::new(2, 1_000_000), Vec2::new(2, -1_000_000), Vec2::new(1_000_002, -3),
+        Vec2::new(1_000_002, 999_997), Vec2::new(-999_996, 1_000_001), Vec2::new(2, 6), Vec2::new(3, 1_000_000),
+    ];
+    let near: Vec<Vec2> = (-8..9).flat_map(|x| (-8..9).map(move |y| Vec2::new(x, y))).collect();
+    assert_matches_state(&state, &far);
+    assert_matches_state(&state, &near);
+
+    let map = state.attack_map(PieceColor::WHITE);
+    assert_eq!(map.attackers(Vec2::new(2, 1_000_000)), vec![Vec2::new(2, -3)]);
+    assert_eq!(map.attackers(Vec2::new(1_000_000, 999_995)), vec![Vec2::new(2, -3)]);
+    assert!(!map.is_attacked(Vec2::new(3, 1_000_000)));
+}
+
+#[test]
+fn test_control_map() {
+    let control = State::default().control_map(PieceColor::WHITE);
+    assert_eq!(control.len(), 64);
+    assert_eq!(control[&Vec2::new(4, 3)], 2);
+    assert_eq!(control[&Vec2::new(6, 3)], 3);
+    assert_eq!(control[&Vec2::new(4, 2)], 4);
+    assert_eq!(control[&Vec2::new(5, 4)], 0);
+
+    // the box around the pieces is grown by the knight's reach of 2
+    let state = StateBuilder::new()
+        .place(PieceType::KING, PieceColor::WHITE, Vec2::ZERO)
+        .place(PieceType::KING, PieceColor::BLACK, Vec2::new(3, 0))
+        .place(PieceType::KNIGHT, PieceColor::WHITE, Vec2::new(1, 1))
+        .build()
+        .unwrap();
+    let control = state.control_map(PieceColor::WHITE);
+    assert_eq!(control.len(), 8 * 6);
+    assert_eq!(control[&Vec2::new(-2, -2)], 0);
+    assert_eq!(control[&Vec2::new(0, 1)], 1);
+    assert_eq!(control[&Vec2::new(2, -1)], 1);
+    assert_eq!(control[&Vec2::new(3, 0)], 1);
+    assert_eq!(control.values().sum::<u32>(), 16);
+    assert!(control.iter().all(|(square, &count)| (count > 0) == state.is_square_attacked(*square, PieceColor::WHITE)));
+}
+
+#[test]
+fn test_control_map_long_leaper() {
+    let mut config = Config::default();
+    let threeleaper = config.register_piece(CustomPiece {
+        name: "Threeleaper".to_owned(), letter: 't', value: 200,
+        leaper_offsets: vec![Vec2::new(3, 0), Vec2::new(-3, 0), Vec2::new(0, 3), Vec2::new(0, -3)], rider_offsets: vec![],
+    }).unwrap();
+    let state = StateBuilder::new()
+        .config(config)
+        .place(PieceType::KING, PieceColor::WHITE, Vec2::new(1, 1))
+        .place(PieceType::KING, PieceColor::BLACK, Vec2::new(4, 1))
+        .place(threeleaper, PieceColor::WHITE, Vec2::new(1, 5))
+        .build()
+        .unwrap();
+    // the leap three squares out of the pieces' box is still counted
+    let control = state.control_map(PieceColor::WHITE);
+    assert!(state.is_square_attacked(Vec2::new(-2, 5), PieceColor::WHITE));
+    assert_eq!(control[&Vec2::new(-2, 5)], 1);
+    assert_eq!(control[&Vec2::new(1, 8)], 1);
+    assert!(control.iter().all(|(square, &count)| (count > 0) == state.is_square_attacked(*square, PieceColor::WHITE)));
+}
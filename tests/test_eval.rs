@@ -0,0 +1,52 @@
+use quasar::eval::*;
+use quasar::pieces::PieceColor;
+use quasar::state::*;
+
+#[test]
+fn test_material_start_position() {
+    let state = State::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1".to_owned()).unwrap();
+    assert_eq!(material(&state), 0);
+}
+
+#[test]
+fn test_material_imbalance() {
+    // white is a queen up, black has an extra knight and pawn
+    let state = State::from_fen("rn2k3/pp6/8/8/8/8/P7/R2QK3 w - - 0 1".to_owned()).unwrap();
+    assert_eq!(material(&state), 900 - 320 - 100);
+    let state = State::from_fen("4k3/8/8/8/8/8/8/4K2r w - - 0 1".to_owned()).unwrap();
+    assert_eq!(material(&state), -500);
+}
+
+#[test]
+fn test_material_checkmate() {
+    let state = State::from_fen("R5k1/5ppp/8/8/8/8/8/6K1 b - - 0 1".to_owned()).unwrap();
+    assert!(state.is_checkmate());
+    assert_eq!(material(&state), MATE);
+    let state = State::from_fen("6k1/8/8/8/8/8/5PPP/r5K1 w - - 0 1".to_owned()).unwrap();
+    assert!(state.is_checkmate());
+    assert_eq!(material(&state), -MATE);
+}
+
+#[test]
+fn test_evaluate_extra_rook() {
+    let state = State::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1".to_owned()).unwrap();
+    assert_eq!(evaluate(&state), 0);
+    let with_rook = State::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 0 1".to_owned()).unwrap();
+    assert!(evaluate(&with_rook) > evaluate(&state) + 500);
+    assert!(mobility(&with_rook, PieceColor::WHITE) > mobility(&state, PieceColor::WHITE));
+}
+
+#[test]
+fn test_evaluate_open_king() {
+    let sheltered = State::from_fen("3qk3/8/8/8/8/8/5PPP/6K1 w - - 0 1".to_owned()).unwrap();
+    let open = State::from_fen("3qk3/8/8/8/8/4K3/5PPP/8 w - - 0 1".to_owned()).unwrap();
+    assert_eq!(king_safety(&sheltered, PieceColor::WHITE), 0);
+    assert!(king_safety(&open, PieceColor::WHITE) < 0);
+    assert!(evaluate(&open) < evaluate(&sheltered));
+}
+
+#[test]
+fn test_evaluate_checkmate() {
+    let state = State::from_fen("R5k1/5ppp/8/8/8/8/8/6K1 b - - 0 1".to_owned()).unwrap();
+    assert_eq!(evaluate(&state), MATE);
+}
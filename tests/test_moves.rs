@@ -0,0 +1,538 @@
+use quasar::state::*;
+use quasar::moves::*;
+use quasar::pieces::*;
+use std::sync::Arc;
+
+use glam::IVec2 as Vec2;
+
+fn legal_ends(state: &State, pos: Vec2) -> Vec<Vec2> {
+    let piece = state.get_piece_at(pos).unwrap().clone();
+    let mut ends: Vec<Vec2> = Generator::new(piece, state.clone()).map(|piece_move| piece_move.end).collect();
+    ends.sort_by_key(|end| (end.x, end.y));
+    ends
+}
+
+// legal moves found by playing every pseudo legal move out, without any shortcut
+fn simulated_moves(state: &State) -> Vec<(Vec2, Vec2, usize)> {
+    let mut moves = vec![];
+    for piece in state.get_pieces().into_iter().filter(|piece| piece.is_alive() && (piece.get_color() == state.to_move)) {
+        let mut generator = Generator::new(piece, state.clone());
+        while !generator.is_depleated() {
+            let Some(piece_move) = generator.next_pseudo() else { continue };
+            if piece_move.castling && (piece_move.piece.get_piece_type() != PieceType::KING) {
+                continue;
+            }
+            if !state.clone().make_move(piece_move.clone()).is_king_in_check(state.to_move) {
+                moves.push((piece_move.start, piece_move.end, piece_move.promotion.map_or(0, |promotion| promotion.index())));
+            }
+        }
+    }
+    moves.sort_by_key(|(start, end, promotion)| (start.x, start.y, end.x, end.y, *promotion));
+    moves
+}
+
+#[test]
+fn test_start_position_pawn_moves() {
+    let state = State::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1".to_owned()).unwrap();
+    let count: usize = state.find(PieceType::PAWN, PieceColor::WHITE).into_iter()
+        .map(|pawn| Generator::new(pawn, state.clone()).count())
+        .sum();
+    assert_eq!(count, 16);
+}
+
+#[test]
+fn test_pinned_piece_has_no_moves() {
+    let state = State::from_fen("4k3/4r3/8/8/8/8/4N3/4K3 w - - 0 1".to_owned()).unwrap();
+    assert!(!state.is_king_in_check(PieceColor::WHITE));
+    assert!(legal_ends(&state, Vec2::new(5, 2)).is_empty());
+}
+
+#[test]
+fn test_pinned_piece_moves_along_pin() {
+    let state = State::from_fen("4k3/4r3/8/8/8/4R3/2B5/B3K3 w - - 0 1".to_owned()).unwrap();
+    let ends = legal_ends(&state, Vec2::new(5, 3));
+    assert_eq!(ends, vec![Vec2::new(5, 2), Vec2::new(5, 4), Vec2::new(5, 5), Vec2::new(5, 6), Vec2::new(5, 7)]);
+    // the bishop on c2 is not on a line with the king
+    assert_eq!(state.pinned_pieces(PieceColor::WHITE), vec![(Vec2::new(5, 3), Vec2::new(0, -1))]);
+}
+
+#[test]
+fn test_king_must_leave_check() {
+    let state = State::from_fen("4k3/8/8/8/8/8/3P1P2/r3K3 w - - 0 1".to_owned()).unwrap();
+    assert!(state.is_king_in_check(PieceColor::WHITE));
+    assert!(!state.is_king_in_check(PieceColor::BLACK));
+    assert_eq!(legal_ends(&state, Vec2::new(5, 1)), vec![Vec2::new(5, 2)]);
+}
+
+#[test]
+fn test_blocked_pawn_cannot_push() {
+    let state = State::from_fen("4k3/8/8/8/8/4n3/4P3/4K3 w - - 0 1".to_owned()).unwrap();
+    assert!(legal_ends(&state, Vec2::new(5, 2)).is_empty());
+}
+
+#[test]
+fn test_every_royal_piece_is_guarded() {
+    let mut state = State::from_fen("4k3/8/8/8/8/8/r7/3QK3 w - - 0 1".to_owned()).unwrap();
+    assert!(!state.is_king_in_check(PieceColor::WHITE));
+    assert_eq!(state.get_royal_pieces(PieceColor::WHITE).len(), 1);
+
+    assert!(state.set_royal_at(Vec2::new(4, 1), true));
+    assert_eq!(state.get_royal_pieces(PieceColor::WHITE).len(), 2);
+    assert!(!state.is_king_in_check(PieceColor::WHITE));
+
+    // the royal queen may not step into the rook's rank
+    assert!(!legal_ends(&state, Vec2::new(4, 1)).contains(&Vec2::new(4, 2)));
+    assert!(legal_ends(&state, Vec2::new(4, 1)).contains(&Vec2::new(4, 3)));
+}
+
+#[test]
+fn test_position_to_algebraic() {
+    assert_eq!(position_to_algebraic(Vec2::new(1, 1)), Some("a1".to_owned()));
+    assert_eq!(position_to_algebraic(Vec2::new(5, 4)), Some("e4".to_owned()));
+    assert_eq!(position_to_algebraic(Vec2::new(8, 8)), Some("h8".to_owned()));
+    assert_eq!(position_to_algebraic(Vec2::new(26, 3)), Some("z3".to_owned()));
+    assert_eq!(position_to_algebraic(Vec2::new(27, 3)), Some("aa3".to_owned()));
+    assert_eq!(position_to_algebraic(Vec2::new(53, 10)), Some("ba10".to_owned()));
+    assert_eq!(position_to_algebraic(Vec2::new(0, 1)), None);
+    assert_eq!(position_to_algebraic(Vec2::new(1, -1)), None);
+}
+
+#[test]
+fn test_algebraic_to_position() {
+    assert_eq!(algebraic_to_position("a1"), Some(Vec2::new(1, 1)));
+    assert_eq!(algebraic_to_position("e4"), Some(Vec2::new(5, 4)));
+    assert_eq!(algebraic_to_position("h8"), Some(Vec2::new(8, 8)));
+    assert_eq!(algebraic_to_position("aa3"), Some(Vec2::new(27, 3)));
+    assert_eq!(algebraic_to_position("ba10"), Some(Vec2::new(53, 10)));
+    assert_eq!(algebraic_to_position("e"), None);
+    assert_eq!(algebraic_to_position("4"), None);
+    assert_eq!(algebraic_to_position("e0"), None);
+    assert_eq!(algebraic_to_position("E4"), None);
+    assert_eq!(algebraic_to_position("e4x"), None);
+}
+
+#[test]
+fn test_algebraic_round_trip() {
+    for x in 1..100 {
+        for y in [1, 8, 1000] {
+            let position = Vec2::new(x, y);
+            assert_eq!(algebraic_to_position(&position_to_algebraic(position).unwrap()), Some(position));
+        }
+    }
+}
+
+#[test]
+fn test_pawns_on_non_standard_ranks() {
+    let state = StateBuilder::new()
+        .place(PieceType::KING, PieceColor::WHITE, Vec2::new(-50, 0))
+        .place(PieceType::KING, PieceColor::BLACK, Vec2::new(50, 100))
+        .place(PieceType::PAWN, PieceColor::WHITE, Vec2::new(0, 50))
+        .place(PieceType::PAWN, PieceColor::BLACK, Vec2::new(1, 52))
+        .build()
+        .unwrap();
+    assert_eq!(legal_ends(&state, Vec2::new(0, 50)), vec![Vec2::new(0, 51), Vec2::new(0, 52)]);
+
+    // the double step can be taken en passant one rank above where the pawn started
+    let double_step = Generator::new(state.get_piece_at(Vec2::new(0, 50)).unwrap().clone(), state.clone())
+        .find(|piece_move| piece_move.end == Vec2::new(0, 52))
+        .unwrap();
+    let state_after = state.clone().make_move(double_step);
+    assert_eq!(state_after.get_en_passant_target(), Some(Vec2::new(0, 51)));
+    assert_eq!(legal_ends(&state_after, Vec2::new(1, 52)), vec![Vec2::new(0, 51), Vec2::new(1, 50), Vec2::new(1, 51)]);
+
+    // once moved, a pawn only single steps or captures
+    let single_step = Generator::new(state.get_piece_at(Vec2::new(0, 50)).unwrap().clone(), state.clone())
+        .find(|piece_move| piece_move.end == Vec2::new(0, 51))
+        .unwrap();
+    let state_after = state.make_move(single_step);
+    let king_move = Generator::new(state_after.get_piece_at(Vec2::new(50, 100)).unwrap().clone(), state_after.clone())
+        .next()
+        .unwrap();
+    let state_after = state_after.make_move(king_move);
+    assert_eq!(legal_ends(&state_after, Vec2::new(0, 51)), vec![Vec2::new(0, 52), Vec2::new(1, 52)]);
+}
+
+#[test]
+fn test_black_pawn_moves() {
+    let state = State::from_fen("4k3/3p4/2P1P3/8/8/8/8/4K3 b - - 0 1".to_owned()).unwrap();
+    assert_eq!(legal_ends(&state, Vec2::new(4, 7)), vec![Vec2::new(3, 6), Vec2::new(4, 5), Vec2::new(4, 6), Vec2::new(5, 6)]);
+
+    let state = State::from_fen("4k3/3p4/8/3N4/8/8/8/4K3 b - - 0 1".to_owned()).unwrap();
+    assert_eq!(legal_ends(&state, Vec2::new(4, 7)), vec![Vec2::new(4, 6)]);
+    let state = State::from_fen("4k3/3p4/3N4/8/8/8/8/4K3 b - - 0 1".to_owned()).unwrap();
+    assert!(legal_ends(&state, Vec2::new(4, 7)).is_empty());
+
+    let state = State::from_fen("4k3/8/3p4/8/8/8/8/4K3 b - - 0 1".to_owned()).unwrap();
+    assert_eq!(legal_ends(&state, Vec2::new(4, 6)), vec![Vec2::new(4, 5)]);
+
+    let state = State::from_fen("4k3/8/8/8/3pP3/8/8/4K3 b - e3 0 1".to_owned()).unwrap();
+    assert_eq!(legal_ends(&state, Vec2::new(4, 4)), vec![Vec2::new(4, 3), Vec2::new(5, 3)]);
+    let en_passant = state.legal_moves_from(Vec2::new(4, 4)).into_iter().find(|piece_move| piece_move.en_passant).unwrap();
+    let state = state.make_move(en_passant);
+    assert!(state.get_piece_at(Vec2::new(5, 4)).is_none());
+
+    let state = State::from_fen("4k3/8/8/8/8/8/3p4/K7 b - - 0 1".to_owned()).unwrap();
+    let promotions = state.legal_moves_from(Vec2::new(4, 2));
+    assert_eq!(promotions.len(), state.config.promotion_options.len());
+    assert!(promotions.iter().all(|piece_move| (piece_move.end == Vec2::new(4, 1)) && piece_move.promotion.is_some()));
+}
+
+#[test]
+fn test_make_move_promotes() {
+    let state = State::from_fen("4k3/P7/8/8/8/8/8/4K3 w - - 0 1".to_owned()).unwrap();
+    let to_rook = state.legal_moves_from(Vec2::new(1, 7)).into_iter().find(|piece_move| piece_move.promotion == Some(PieceType::ROOK)).unwrap();
+    let promoted = state.clone().make_move(to_rook.clone());
+    let rook = promoted.get_piece_at(Vec2::new(1, 8)).unwrap();
+    assert_eq!(rook.get_piece_type(), PieceType::ROOK);
+    assert_eq!(rook.get_id(), to_rook.piece.get_id());
+    assert!(promoted.is_king_in_check(PieceColor::BLACK));
+
+    // the new rook moves like one on the following turn
+    let king_move = promoted.get_legal_moves().remove(0);
+    let promoted = promoted.make_move(king_move);
+    assert!(legal_ends(&promoted, Vec2::new(1, 8)).contains(&Vec2::new(1, 1)));
+
+    let mut undone = state.clone().make_move(to_rook.clone());
+    assert_eq!(undone.unmake_move().unwrap(), Some(to_rook));
+    assert_eq!(undone.get_piece_at(Vec2::new(1, 7)).unwrap().get_piece_type(), PieceType::PAWN);
+    assert_eq!(undone, state);
+
+    let king = state.get_piece_at(Vec2::new(5, 1)).unwrap().clone();
+    let bad = Move::new(Vec2::new(5, 1), Vec2::new(5, 2), king, None, Some(PieceType::QUEEN), false, None, false);
+    assert!(state.try_make_move(bad).is_err());
+}
+
+#[test]
+fn test_pawn_off_home_rank_from_fen() {
+    let state = State::from_fen("4k3/8/8/8/8/4P3/8/4K3 w - - 0 1".to_owned()).unwrap();
+    assert_eq!(legal_ends(&state, Vec2::new(5, 3)), vec![Vec2::new(5, 4)]);
+}
+
+#[test]
+fn test_legality_shortcut_matches_simulation() {
+    let fens = [
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        "8/8/8/K2pP2r/8/8/8/7k w - d6 0 1",
+        "4k3/4r3/8/1b6/8/3NR3/8/4K3 w - - 0 1",
+        "4k3/8/8/8/8/8/3B4/r3K2b w - - 0 1",
+    ];
+    for fen in fens {
+        let state = State::from_fen(fen.to_owned()).unwrap();
+        let mut moves: Vec<(Vec2, Vec2, usize)> = state.legal_moves_iter()
+            .map(|piece_move| (piece_move.start, piece_move.end, piece_move.promotion.map_or(0, |promotion| promotion.index())))
+            .collect();
+        moves.sort_by_key(|(start, end, promotion)| (start.x, start.y, end.x, end.y, *promotion));
+        assert_eq!(moves, simulated_moves(&state), "{}", fen);
+    }
+
+    // the en passant capture would open the rank to the rook
+    let state = State::from_fen(fens[2].to_owned()).unwrap();
+    assert_eq!(legal_ends(&state, Vec2::new(5, 5)), vec![Vec2::new(5, 6)]);
+}
+
+#[test]
+fn test_promotion_options() {
+    let mut state = State::from_fen("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1".to_owned()).unwrap();
+    let pawn = state.get_piece_at(Vec2::new(2, 7)).unwrap().clone();
+    assert_eq!(Generator::new(pawn.clone(), state.clone()).count(), 4);
+    let all_moves = state.get_legal_moves().len();
+
+    state.config.promotion_options = vec![PieceType::QUEEN];
+    let promotions: Vec<Option<PieceType>> = Generator::new(pawn, state.clone()).map(|piece_move| piece_move.promotion).collect();
+    assert_eq!(promotions, vec![Some(PieceType::QUEEN)]);
+    assert_eq!(state.get_legal_moves().len(), all_moves - 3);
+}
+
+#[test]
+fn test_move_equality() {
+    let state = State::from_fen("4k3/4r3/8/8/8/8/4N3/4K3 w - - 0 1".to_owned()).unwrap();
+    let knight = state.get_piece_at(Vec2::new(5, 2)).unwrap().clone();
+    let mut generator = Generator::new(knight.clone(), state.clone());
+    let pseudo = loop {
+        if let Some(piece_move) = generator.next_pseudo() {
+            break piece_move;
+        }
+    };
+    // running the move through the legality check leaves it unchanged
+    let copy = pseudo.clone();
+    assert!(state.clone().make_move(pseudo.clone()).is_king_in_check(PieceColor::WHITE));
+    assert_eq!(pseudo, copy);
+
+    let quiet = Move::new(Vec2::new(5, 2), Vec2::new(3, 3), knight.clone(), None, None, false, None, false);
+    assert_eq!(quiet, Move::new(Vec2::new(5, 2), Vec2::new(3, 3), knight.clone(), None, None, false, None, false));
+    assert_ne!(quiet, Move::new(Vec2::new(5, 2), Vec2::new(4, 4), knight, None, None, false, None, false));
+}
+
+#[test]
+fn test_moves_near_coordinate_limits() {
+    let max = i32::MAX;
+    let min = i32::MIN;
+    let state = StateBuilder::new()
+        .place(PieceType::KING, PieceColor::WHITE, Vec2::new(max - 2, max - 2))
+        .place(PieceType::KNIGHT, PieceColor::WHITE, Vec2::new(max - 1, max - 1))
+        .place(PieceType::KING, PieceColor::BLACK, Vec2::new(min + 1, min + 1))
+        .build()
+        .unwrap();
+    // knight jumps past the last coordinate are dropped instead of overflowing
+    assert_eq!(legal_ends(&state, Vec2::new(max - 1, max - 1)), vec![Vec2::new(max - 3, max - 2), Vec2::new(max - 2, max - 3)]);
+    assert_eq!(state.get_legal_moves().len(), 9);
+    assert_eq!(state.ray_attacks(Vec2::new(max - 1, max - 1), Vec2::new(1, 1)), vec![]);
+
+    // a rook further away than an i32 can span still gives check along the rank
+    let state = StateBuilder::new()
+        .place(PieceType::KING, PieceColor::WHITE, Vec2::new(max - 2, max - 2))
+        .place(PieceType::KNIGHT, PieceColor::WHITE, Vec2::new(max - 1, max - 1))
+        .place(PieceType::KING, PieceColor::BLACK, Vec2::new(min + 1, min + 1))
+        .place(PieceType::ROOK, PieceColor::BLACK, Vec2::new(min + 1, max - 2))
+        .build()
+        .unwrap();
+    assert!(state.is_king_in_check(PieceColor::WHITE));
+    assert_eq!(legal_ends(&state, Vec2::new(max - 1, max - 1)), vec![Vec2::new(max - 3, max - 2)]);
+    assert_eq!(state.get_legal_moves().len(), 6);
+    assert!(state.is_square_attacked(Vec2::new(max - 3, max - 2), PieceColor::BLACK));
+    assert!(!state.is_square_attacked(Vec2::new(max - 3, max - 3), PieceColor::BLACK));
+}
+
+#[test]
+fn test_knight_surrounded_by_own_pawns() {
+    let state = State::from_fen("k7/8/3P1P2/2PPPPP1/3PNP2/3PPP2/8/K7 w - - 0 1".to_owned()).unwrap();
+    let expected = vec![Vec2::new(3, 3), Vec2::new(4, 2), Vec2::new(6, 2), Vec2::new(7, 3)];
+    assert_eq!(legal_ends(&state, Vec2::new(5, 4)), expected);
+
+    // the squares held by its own pawns are not even offered as pseudo legal moves
+    let mut generator = Generator::new(state.get_piece_at(Vec2::new(5, 4)).unwrap().clone(), state.clone());
+    let mut pseudo = vec![];
+    while !generator.is_depleated() {
+        pseudo.extend(generator.next_pseudo());
+    }
+    assert_eq!(pseudo.len(), 4);
+    assert!(pseudo.iter().all(|piece_move| piece_move.target.is_none()));
+}
+
+#[test]
+fn test_generator_clone_and_reset() {
+    let state = State::from_fen("4k3/8/8/8/3R4/8/8/4K3 w - - 0 1".to_owned()).unwrap();
+    let rook = state.get_piece_at(Vec2::new(4, 4)).unwrap().clone();
+    let mut generator = Generator::new(rook, state.clone());
+    let first: Vec<Vec2> = generator.by_ref().take(5).map(|piece_move| piece_move.end).collect();
+    assert_eq!(first.len(), 5);
+
+    let fork = generator.clone();
+    let rest: Vec<Vec2> = generator.by_ref().map(|piece_move| piece_move.end).collect();
+    assert_eq!(fork.map(|piece_move| piece_move.end).collect::<Vec<Vec2>>(), rest);
+    assert_eq!(first.len() + rest.len(), 14);
+
+    generator.reset();
+    assert_eq!(generator.map(|piece_move| piece_move.end).collect::<Vec<Vec2>>(), [first, rest].concat());
+}
+
+#[test]
+fn test_generator_remaining_is_bounded() {
+    let state = StateBuilder::new()
+        .place(PieceType::KING, PieceColor::WHITE, Vec2::new(0, 0))
+        .place(PieceType::KING, PieceColor::BLACK, Vec2::new(0, 10))
+        .place(PieceType::ROOK, PieceColor::WHITE, Vec2::new(5, 5))
+        .place(PieceType::KNIGHT, PieceColor::WHITE, Vec2::new(5, 0))
+        .place(PieceType::PAWN, PieceColor::BLACK, Vec2::new(5, 20))
+        .place(PieceType::PAWN, PieceColor::BLACK, Vec2::new(-3, 5))
+        .build()
+        .unwrap();
+    let rook = Generator::new(state.get_piece_at(Vec2::new(5, 5)).unwrap().clone(), state.clone());
+    assert!(!rook.remaining_is_bounded());
+    assert!(Generator::new(state.get_piece_at(Vec2::new(5, 0)).unwrap().clone(), state.clone()).remaining_is_bounded());
+
+    // a fourth blocker closes the last open ray
+    let state = StateBuilder::new()
+        .place(PieceType::KING, PieceColor::WHITE, Vec2::new(0, 0))
+        .place(PieceType::KING, PieceColor::BLACK, Vec2::new(0, 10))
+        .place(PieceType::ROOK, PieceColor::WHITE, Vec2::new(5, 5))
+        .place(PieceType::KNIGHT, PieceColor::WHITE, Vec2::new(5, 0))
+        .place(PieceType::PAWN, PieceColor::BLACK, Vec2::new(5, 20))
+        .place(PieceType::PAWN, PieceColor::BLACK, Vec2::new(-3, 5))
+        .place(PieceType::PAWN, PieceColor::BLACK, Vec2::new(1000, 5))
+        .build()
+        .unwrap();
+    let mut rook = Generator::new(state.get_piece_at(Vec2::new(5, 5)).unwrap().clone(), state.clone());
+    assert!(rook.remaining_is_bounded());
+    assert_eq!(rook.by_ref().count(), 1022);
+    assert!(rook.remaining_is_bounded());
+}
+
+#[test]
+fn test_generator_on_infinite_board_can_be_bounded() {
+    let state = StateBuilder::new()
+        .place(PieceType::KING, PieceColor::WHITE, Vec2::new(0, 0))
+        .place(PieceType::KING, PieceColor::BLACK, Vec2::new(0, 10))
+        .place(PieceType::BISHOP, PieceColor::WHITE, Vec2::new(5, 5))
+        .build()
+        .unwrap();
+    let bishop = Generator::new(state.get_piece_at(Vec2::new(5, 5)).unwrap().clone(), state.clone());
+    assert!(!bishop.remaining_is_bounded());
+    let ends: Vec<Vec2> = bishop.take(3).map(|piece_move| piece_move.end).collect();
+    assert_eq!(ends.len(), 3);
+    assert!(ends.iter().all(|end| (*end - Vec2::new(5, 5)).abs().x == (*end - Vec2::new(5, 5)).abs().y));
+}
+
+#[test]
+fn test_gives_check_and_checkmate() {
+    let state = State::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1".to_owned()).unwrap();
+    let rook_move = |end: Vec2| state.legal_moves_from(Vec2::new(1, 1)).into_iter().find(|piece_move| piece_move.end == end).unwrap();
+
+    let mate = rook_move(Vec2::new(1, 8));
+    assert!(mate.gives_check(&state));
+    assert!(mate.gives_checkmate(&state));
+
+    let quiet = rook_move(Vec2::new(1, 7));
+    assert!(!quiet.gives_check(&state));
+    assert!(!quiet.gives_checkmate(&state));
+
+    let state = State::from_fen("6k1/6pp/8/8/8/8/8/R5K1 w - - 0 1".to_owned()).unwrap();
+    let check = state.legal_moves_from(Vec2::new(1, 1)).into_iter().find(|piece_move| piece_move.end == Vec2::new(1, 8)).unwrap();
+    assert!(check.gives_check(&state));
+    assert!(!check.gives_checkmate(&state));
+}
+
+#[test]
+fn test_custom_pieces() {
+    let knight = PieceType::KNIGHT.base_offsets(PieceColor::WHITE);
+    let mut registry = PieceRegistry::default();
+    let amazon = registry.register(CustomPiece {
+        name: "Amazon".to_owned(), letter: 'a', value: 1200,
+        leaper_offsets: knight.clone(), rider_offsets: PieceType::QUEEN.base_offsets(PieceColor::WHITE),
+    }).unwrap();
+    let archbishop = registry.register(CustomPiece {
+        name: "Archbishop".to_owned(), letter: 'c', value: 850,
+        leaper_offsets: knight.clone(), rider_offsets: PieceType::BISHOP.base_offsets(PieceColor::WHITE),
+    }).unwrap();
+    registry.register(CustomPiece {
+        name: "Nightrider".to_owned(), letter: 'h', value: 500,
+        leaper_offsets: vec![], rider_offsets: knight,
+    }).unwrap();
+    registry.register(CustomPiece {
+        name: "Dabbaba rider".to_owned(), letter: 'd', value: 400,
+        leaper_offsets: vec![], rider_offsets: vec![Vec2::new(2, 0), Vec2::new(-2, 0), Vec2::new(0, 2), Vec2::new(0, -2)],
+    }).unwrap();
+    let registry = Arc::new(registry);
+    let from_fen = |fen: &str| State::from_fen_with_pieces(fen.to_owned(), 8, 8, registry.clone()).unwrap();
+    
+    // the letters mean nothing to a game without them
+    assert!(State::from_fen("4k3/8/8/8/3A4/8/8/K7 w - - 0 1".to_owned()).is_err());
+
+    // the queen's 26 squares with the own king on a1, and all 8 knight jumps
+    let state = from_fen("4k3/8/8/8/3A4/8/8/K7 w - - 0 1");
+    assert_eq!(state.get_piece_at(Vec2::new(4, 4)).unwrap().get_piece_type(), amazon);
+    assert_eq!(legal_ends(&state, Vec2::new(4, 4)).len(), 34);
+    assert_eq!(state.to_fen(), "4k3/8/8/8/3A4/8/8/K7 w - - 0 1");
+
+    // the jump checks and the rides cover every square the king could go to
+    let state = from_fen("4k3/8/3A4/8/8/8/8/K7 b - - 0 1");
+    assert_eq!(state.checkers(PieceColor::BLACK), vec![Vec2::new(4, 6)]);
+    assert!(state.is_checkmate());
+
+    let state = from_fen("4k3/8/8/8/8/3c4/8/4K3 w - - 0 1");
+    assert_eq!(state.get_piece_at(Vec2::new(4, 3)).unwrap().get_piece_type(), archbishop);
+    assert!(state.is_king_in_check(PieceColor::WHITE));
+
+    // a knight pinned along the nightrider's ride can only take it
+    let state = from_fen("4k3/8/8/6h1/8/5N2/8/4K3 w - - 0 1");
+    assert_eq!(legal_ends(&state, Vec2::new(6, 3)), vec![Vec2::new(7, 5)]);
+
+    // a straight ride that skips squares pins too, none of the knight's moves are legal
+    let state = from_fen("4k3/8/8/8/8/8/8/K1N1d3 w - - 0 1");
+    assert!(legal_ends(&state, Vec2::new(3, 1)).is_empty());
+
+    for fen in ["4k3/8/8/8/3A4/8/8/K7 w - - 0 1", "4k3/8/1A6/8/8/3c4/8/4K3 w - - 0 1", "4k3/8/8/6h1/8/5N2/8/4K3 w - - 0 1", "4k3/8/8/8/8/8/8/K1N1d3 w - - 0 1"] {
+        let state = from_fen(fen);
+        let mut moves: Vec<(Vec2, Vec2, usize)> = state.legal_moves_iter()
+            .map(|piece_move| (piece_move.start, piece_move.end, piece_move.promotion.map_or(0, |promotion| promotion.index())))
+            .collect();
+        moves.sort_by_key(|(start, end, promotion)| (start.x, start.y, end.x, end.y, *promotion));
+        assert_eq!(moves, simulated_moves(&state), "{}", fen);
+    }
+}
+
+fn san(state: &State, uci: &str) -> String {
+    let piece_move = state.get_legal_moves().into_iter().find(|piece_move| piece_move.to_uci(state).unwrap() == uci).unwrap();
+    piece_move.to_san(state).unwrap()
+}
+
+#[test]
+fn test_to_san() {
+    let state = State::default();
+    assert_eq!(san(&state, "g1f3"), "Nf3");
+    assert_eq!(san(&state, "e2e4"), "e4");
+
+    // captures, en passant included
+    let state = State::from_fen("rnbqkbnr/ppp2ppp/8/3pp3/4P3/2N5/PPPP1PPP/R1BQKBNR w KQkq - 0 3".to_owned()).unwrap();
+    assert_eq!(san(&state, "e4d5"), "exd5");
+    assert_eq!(san(&state, "c3d5"), "Nxd5");
+    let state = State::from_fen("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3".to_owned()).unwrap();
+    assert_eq!(san(&state, "e5f6"), "exf6");
+
+    // both sides, whichever piece the move belongs to
+    let state = State::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1".to_owned()).unwrap();
+    assert_eq!(san(&state, "e1g1"), "O-O");
+    assert_eq!(san(&state, "e1c1"), "O-O-O");
+
+    // promotions, with check when the new piece gives it
+    let state = State::from_fen("k7/4P3/8/8/8/8/8/4K3 w - - 0 1".to_owned()).unwrap();
+    assert_eq!(san(&state, "e7e8q"), "e8=Q+");
+    assert_eq!(san(&state, "e7e8n"), "e8=N");
+
+    // by file, by rank, and by both when neither is enough
+    let state = State::from_fen("4k3/8/8/8/8/8/8/1N2KN2 w - - 0 1".to_owned()).unwrap();
+    assert_eq!(san(&state, "b1d2"), "Nbd2");
+    let state = State::from_fen("4k3/8/R7/8/8/8/8/R3K3 w - - 0 1".to_owned()).unwrap();
+    assert_eq!(san(&state, "a1a3"), "R1a3");
+    let state = State::from_fen("4k3/8/8/8/7Q/8/8/4K2Q w - - 0 1".to_owned()).unwrap();
+    assert_eq!(san(&state, "h4e4"), "Q4e4+");
+    let state = State::from_fen("2k5/8/8/8/4Q2Q/8/K7/7Q w - - 0 1".to_owned()).unwrap();
+    assert_eq!(san(&state, "h4e1"), "Qh4e1");
+
+    // a pinned piece does not count as a second one
+    let state = State::from_fen("k3r3/8/8/8/8/8/4N3/1N2K3 w - - 0 1".to_owned()).unwrap();
+    assert_eq!(san(&state, "b1c3"), "Nc3");
+
+    let state = State::from_fen("r1bqkb1r/pppp1ppp/2n2n2/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - 4 4".to_owned()).unwrap();
+    assert_eq!(san(&state, "h5f7"), "Qxf7#");
+}
+
+#[test]
+fn test_legal_moves_are_sorted() {
+    let state = State::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1".to_owned()).unwrap();
+    let moves = state.get_legal_moves();
+    assert!(moves.windows(2).all(|pair| pair[0] < pair[1]));
+    let uci: Vec<String> = moves.iter().map(|piece_move| piece_move.to_uci(&state).unwrap()).collect();
+    let mut sorted = uci.clone();
+    sorted.sort();
+    assert_eq!(uci, sorted);
+
+    // no promotion comes first, then the pieces by letter
+    let state = State::from_fen("4k3/P7/8/8/8/8/8/4K3 w - - 0 1".to_owned()).unwrap();
+    let promotions: Vec<String> = state.get_legal_moves().into_iter()
+        .filter(|piece_move| piece_move.start == Vec2::new(1, 7))
+        .map(|piece_move| piece_move.to_uci(&state).unwrap())
+        .collect();
+    assert_eq!(promotions, vec!["a7a8b", "a7a8n", "a7a8q", "a7a8r"]);
+
+    // moves on the same squares that are not equal do not compare equal either
+    let push = State::default().legal_moves_from(Vec2::new(5, 2)).remove(0);
+    let mut moved = push.clone();
+    moved.piece.moved();
+    assert_ne!(push, moved);
+    assert_ne!(push.cmp(&moved), std::cmp::Ordering::Equal);
+    assert_eq!(push.cmp(&push.clone()), std::cmp::Ordering::Equal);
+    assert_eq!(std::collections::BTreeSet::from([push, moved]).len(), 2);
+}
+
+#[test]
+fn test_move_display() {
+    let state = State::from_fen("rn2k2r/P7/8/3pP3/8/8/8/R3K2R w KQkq d6 0 1".to_owned()).unwrap();
+    let shown: Vec<String> = state.get_legal_moves().iter().map(|piece_move| piece_move.to_string()).collect();
+    assert!(shown.contains(&"P5,5>5,6".to_owned()));
+    assert!(shown.contains(&"P5,5>4,6 ep".to_owned()));
+    assert!(shown.contains(&"P1,7>2,8=Q".to_owned()));
+    assert!(shown.contains(&"K5,1>8,1 O-O".to_owned()));
+    assert!(shown.contains(&"K5,1>1,1 O-O-O".to_owned()));
+}
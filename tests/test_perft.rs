@@ -0,0 +1,80 @@
+use quasar::perft::*;
+use quasar::positions::*;
+use quasar::state::*;
+
+// expected holds the node counts from depth 0 up
+fn assert_perft(fen: &str, expected: &[u64]) {
+    let state = State::from_start_fen(fen).unwrap();
+    for (depth, nodes) in expected.iter().enumerate() {
+        assert_eq!(perft(&state, depth), *nodes, "{} at depth {}", fen, depth);
+    }
+}
+
+#[test]
+fn test_perft_start_position() {
+    assert_perft(START, &[1, 20, 400, 8902]);
+}
+
+#[test]
+fn test_perft_positions() {
+    assert_perft(KIWIPETE, &[1, 48, 2039]);
+    assert_perft(POSITION_3, &[1, 14, 191, 2812]);
+    assert_perft(POSITION_4, &[1, 6, 264, 9467]);
+    assert_perft(POSITION_5, &[1, 44, 1486]);
+    assert_perft(POSITION_6, &[1, 46, 2079]);
+}
+
+#[test]
+fn test_perft_divide() {
+    for (fen, depth) in [(START, 3), (KIWIPETE, 2)] {
+        let state = State::from_fen(fen.to_owned()).unwrap();
+        let divided = perft_divide(&state, depth);
+        assert_eq!(divided.len(), state.get_legal_moves().len());
+        assert_eq!(divided.iter().map(|(_, nodes)| nodes).sum::<u64>(), perft(&state, depth), "{}", fen);
+    }
+
+    // each root move on its own, in sorted order
+    let divided = perft_divide(&State::default(), 2);
+    assert!(divided.iter().all(|(_, nodes)| *nodes == 20));
+    assert_eq!(divided[0].0.to_uci(&State::default()).unwrap(), "a2a3");
+    assert!(perft_divide(&State::default(), 0).is_empty());
+}
+
+#[test]
+fn test_perft_parallel() {
+    let state = State::from_fen(KIWIPETE.to_owned()).unwrap();
+    let expected = perft(&state, 2);
+    for threads in [0, 1, 3, 8, 100] {
+        assert_eq!(perft_parallel(&state, 2, threads), expected);
+    }
+    assert_eq!(perft_parallel(&State::default(), 3, 4), 8902);
+    assert_eq!(perft_parallel(&state, 0, 4), 1);
+}
+
+#[test]
+fn test_perft_detailed_start_position() {
+    let stats = perft_detailed(&State::default(), 3);
+    assert_eq!(stats, PerftStats { nodes: 8902, captures: 34, en_passant: 0, castles: 0, promotions: 0, checks: 12, checkmates: 0 });
+}
+
+#[test]
+fn test_perft_detailed_kiwipete() {
+    let state = State::from_fen(KIWIPETE.to_owned()).unwrap();
+    let stats = perft_detailed(&state, 1);
+    assert_eq!(stats, PerftStats { nodes: 48, captures: 8, en_passant: 0, castles: 2, promotions: 0, checks: 0, checkmates: 0 });
+    let stats = perft_detailed(&state, 2);
+    assert_eq!(stats, PerftStats { nodes: 2039, captures: 351, en_passant: 1, castles: 91, promotions: 0, checks: 3, checkmates: 0 });
+}
+
+#[test]
+fn test_perft_detailed_endgame() {
+    let state = State::from_start_fen(POSITION_3).unwrap();
+    assert_eq!(perft_detailed(&state, 1), PerftStats { nodes: 14, captures: 1, en_passant: 0, castles: 0, promotions: 0, checks: 2, checkmates: 0 });
+    assert_eq!(perft_detailed(&state, 2), PerftStats { nodes: 191, captures: 14, en_passant: 0, castles: 0, promotions: 0, checks: 10, checkmates: 0 });
+}
+
+#[test]
+fn test_perft_from_fen() {
+    assert_eq!(State::perft_from_fen(KIWIPETE, 1), Ok(48));
+    assert!(State::perft_from_fen("not a fen", 1).is_err());
+}
@@ -0,0 +1,98 @@
+use quasar::pgn::*;
+use quasar::pieces::*;
+use quasar::state::*;
+
+fn play(state: State, moves: &[&str]) -> State {
+    moves.iter().fold(state, |state, uci| state.apply_uci(uci).unwrap())
+}
+
+#[test]
+fn test_export_pgn() {
+    let state = play(State::default(), &["e2e4", "e7e5", "f1c4", "b8c6", "d1h5", "g8f6", "h5f7"]);
+    assert_eq!(export_pgn(&state).unwrap(), "\
+[Event \"?\"]
+[Site \"?\"]
+[Date \"????.??.??\"]
+[Round \"?\"]
+[White \"?\"]
+[Black \"?\"]
+[Result \"1-0\"]
+
+1. e4 e5 2. Bc4 Nc6 3. Qh5 Nf6 4. Qxf7# 1-0
+");
+
+    // an unfinished game, and one that was given up
+    assert!(export_pgn(&State::default()).unwrap().ends_with("[Result \"*\"]\n\n*\n"));
+    let resigned = play(State::default(), &["e2e4"]).resign(PieceColor::BLACK);
+    assert!(export_pgn(&resigned).unwrap().ends_with("\n1. e4 1-0\n"));
+}
+
+#[test]
+fn test_export_pgn_from_fen() {
+    let fen = "r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 0 12";
+    let state = play(State::from_fen(fen.to_owned()).unwrap(), &["e8h8", "e1a1"]);
+    let pgn = export_pgn(&state).unwrap();
+    assert!(pgn.contains(&format!("[SetUp \"1\"]\n[FEN \"{}\"]\n", fen)));
+    assert!(pgn.ends_with("\n12... O-O 13. O-O-O *\n"));
+}
+
+#[test]
+fn test_export_pgn_compact() {
+    // the captured pawns are gone from the final position but not from the start it is played from
+    let state = State::default().with_capture_mode(CaptureMode::Compact);
+    let state = play(state, &["e2e4", "d7d5", "e4d5", "d8d5", "b1c3", "d5a5"]);
+    assert_eq!(state.get_pieces().len(), 30);
+    assert!(export_pgn(&state).unwrap().ends_with("\n1. e4 d5 2. exd5 Qxd5 3. Nc3 Qa5 *\n"));
+}
+
+#[test]
+fn test_export_pgn_line_width() {
+    let mut state = State::default();
+    for _ in 0..10 {
+        state = play(state, &["g1f3", "g8f6", "f3g1", "f6g8"]);
+    }
+    let pgn = export_pgn(&state).unwrap();
+    assert!(pgn.lines().all(|line| line.len() < 80));
+    assert!(pgn.ends_with(" 20. Ng1 Ng8 1/2-1/2\n"));
+
+    assert!(export_pgn(&state.make_null_move().unwrap()).is_err());
+}
+
+#[test]
+fn test_import_pgn() {
+    let state = play(State::default(), &["e2e4", "e7e5", "f1c4", "b8c6", "d1h5", "g8f6", "h5f7"]);
+    let states = import_pgn(&export_pgn(&state).unwrap()).unwrap();
+    assert_eq!(states.len(), 7);
+    assert_eq!(states.last().unwrap(), &state);
+    assert_eq!(states.last().unwrap().result(), GameResult::WhiteWins);
+    assert_eq!(states[0].to_fen(), "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1");
+
+    // comments, annotations, variations and glued move numbers are all skipped over
+    let pgn = "[Event \"Casual\"]
+[Result \"*\"]
+
+1.e4 {best by test} e5 2. Nf3 $1 (2. f4 exf4 (2... d5)) Nc6 ; the usual
+3. Bb5!? a6 4. Bxc6 dxc6 5. O-O *";
+    let states = import_pgn(pgn).unwrap();
+    assert_eq!(states.len(), 9);
+    assert_eq!(states.last().unwrap().to_fen(), "r1bqkbnr/1pp2ppp/p1p5/4p3/4P3/5N2/PPPP1PPP/RNBQ1RK1 b kq - 1 5");
+
+    // a setup position, promotion and a move disambiguated more than needed
+    let pgn = "[SetUp \"1\"]\n[FEN \"4k3/P7/8/8/8/8/8/1N2K1N1 w - - 0 1\"]\n\n1. a8=Q+ Kd7 2. Nb1d2 *";
+    let states = import_pgn(pgn).unwrap();
+    assert_eq!(states[0].get_piece_at(glam::IVec2::new(1, 8)).unwrap().get_piece_type(), PieceType::QUEEN);
+    assert_eq!(states[2].history().last().unwrap().start, glam::IVec2::new(2, 1));
+}
+
+#[test]
+fn test_import_pgn_errors() {
+    let error = import_pgn("1. e4 e5 2. Ke3 *").unwrap_err();
+    assert!(error.contains("'Ke3'") && error.contains("ply 3"), "{}", error);
+
+    let error = import_pgn("[FEN \"4k3/8/8/8/8/8/8/1N2KN2 w - - 0 1\"]\n1. Nd2").unwrap_err();
+    assert!(error.contains("'Nd2'") && error.contains("ambiguous"), "{}", error);
+
+    assert!(import_pgn("1. e4 (1. d4").is_err());
+    assert!(import_pgn("1. e4 {unclosed").is_err());
+    assert!(import_pgn("").unwrap().is_empty());
+}
@@ -1,3 +1,6 @@
+// the older tests compare booleans with assert_eq
+#![allow(clippy::bool_assert_comparison)]
+
 use quasar::pieces::*;
 use glam::IVec2 as Vec2;
 
@@ -12,6 +15,7 @@ fn test_piece_struct() {
     assert_eq!(piece.get_symbol(), 'P');
     assert_eq!(piece.has_moved(), false);
     assert_eq!(piece.is_alive(), true);
+    assert!(!piece.is_royal());
 }
 
 #[test]
@@ -30,6 +34,15 @@ fn test_capture() {
     assert_eq!(piece.is_alive(), false);
 }
 
+#[test]
+fn test_revive() {
+    let mut piece = Piece::new(PieceColor::BLACK, PieceType::ROOK, Vec2::ONE);
+    piece.capture();
+    piece.revive();
+    assert!(piece.is_alive());
+    assert_eq!(piece.get_position(), &Vec2::ONE);
+}
+
 #[test]
 fn test_moved() {
     let mut piece = Piece::new(PieceColor::WHITE, PieceType::PAWN, Vec2::ZERO);
@@ -44,6 +57,20 @@ fn test_color_from_bool() {
     assert_eq!(PieceColor::WHITE, PieceColor::from_bool(true));
 }
 
+#[test]
+fn test_color_helpers() {
+    assert_eq!(PieceColor::WHITE.opposite(), PieceColor::BLACK);
+    assert_eq!(PieceColor::BLACK.opposite(), PieceColor::WHITE);
+    assert_eq!(PieceColor::WHITE.direction(), 1);
+    assert_eq!(PieceColor::BLACK.direction(), -1);
+    assert_eq!(PieceColor::WHITE.forward(), Vec2::new(0, 1));
+    assert_eq!(PieceColor::BLACK.forward(), Vec2::new(0, -1));
+    assert_eq!(PieceColor::from(true), PieceColor::WHITE);
+    assert_eq!(PieceColor::from(false), PieceColor::BLACK);
+    assert!(bool::from(PieceColor::WHITE));
+    assert!(!bool::from(PieceColor::BLACK));
+}
+
 #[test]
 fn test_type_from_int() {
     assert_eq!(PieceType::NULL, PieceType::from_u8(0));
@@ -99,4 +126,72 @@ fn test_symbol_to_name() {
     assert_eq!(symbol_to_name('q'), "queen");
     assert_eq!(symbol_to_name('K'), "king");
     assert_eq!(symbol_to_name('x'), "null")
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_royal() {
+    let mut king = Piece::new(PieceColor::WHITE, PieceType::KING, Vec2::ZERO);
+    assert!(king.is_royal());
+    king.set_royal(false);
+    assert!(!king.is_royal());
+}
+
+#[test]
+fn test_type_letters_and_values() {
+    for piece_type in [PieceType::PAWN, PieceType::KNIGHT, PieceType::BISHOP, PieceType::ROOK, PieceType::QUEEN, PieceType::KING] {
+        assert_eq!(PieceType::from_letter(piece_type.letter()), piece_type);
+        assert_eq!(PieceType::from_letter(piece_type.letter().to_ascii_uppercase()), piece_type);
+    }
+    assert_eq!(PieceType::NULL.letter(), 'x');
+    assert_eq!(PieceType::from_letter('z'), PieceType::NULL);
+    assert_eq!(PieceType::NULL.value(), 0);
+    assert_eq!(PieceType::KING.value(), 0);
+    assert!(PieceType::QUEEN.value() > PieceType::ROOK.value());
+    assert!(PieceType::KNIGHT.value() > PieceType::PAWN.value());
+}
+
+#[test]
+fn test_base_offsets() {
+    assert_eq!(PieceType::PAWN.base_offsets(PieceColor::WHITE)[0], Vec2::new(0, 1));
+    assert_eq!(PieceType::PAWN.base_offsets(PieceColor::BLACK)[0], Vec2::new(0, -1));
+    assert_eq!(PieceType::PAWN.base_offsets(PieceColor::BLACK)[1..], [Vec2::new(1, -1), Vec2::new(-1, -1)]);
+    assert!(PieceType::KNIGHT.base_offsets(PieceColor::WHITE).iter().all(|offset| offset.x.abs() * offset.y.abs() == 2));
+    assert_eq!(PieceType::QUEEN.base_offsets(PieceColor::WHITE), PieceType::KING.base_offsets(PieceColor::BLACK));
+    assert!(PieceType::NULL.base_offsets(PieceColor::WHITE).is_empty());
+    for piece_type in [PieceType::KNIGHT, PieceType::BISHOP, PieceType::ROOK, PieceType::QUEEN, PieceType::KING] {
+        assert_eq!(piece_type.base_offsets(PieceColor::WHITE), piece_type.base_offsets(PieceColor::BLACK));
+    }
+
+    let sliding: Vec<PieceType> = (0..7).map(PieceType::from_u8).filter(PieceType::is_sliding).collect();
+    assert_eq!(sliding, vec![PieceType::BISHOP, PieceType::ROOK, PieceType::QUEEN]);
+}
+
+#[test]
+fn test_register_custom_piece() {
+    let alfil = CustomPiece {
+        name: "Alfil".to_owned(), letter: 'E', value: 150,
+        leaper_offsets: vec![Vec2::new(2, 2), Vec2::new(2, -2), Vec2::new(-2, 2), Vec2::new(-2, -2)], rider_offsets: vec![],
+    };
+    let mut registry = PieceRegistry::default();
+    let piece_type = registry.register(alfil.clone()).unwrap();
+    assert_eq!(registry.get(piece_type).unwrap().name, "Alfil");
+    assert_eq!(registry.letter(piece_type), 'e');
+    assert_eq!(registry.symbol(&Piece::new(PieceColor::WHITE, piece_type, Vec2::ZERO)), 'E');
+    assert_eq!(registry.from_letter('E'), piece_type);
+    assert_eq!(registry.from_letter('Q'), PieceType::QUEEN);
+    assert_eq!(registry.value(piece_type), 150);
+    assert_eq!(registry.name(piece_type), "alfil");
+    assert_eq!(registry.base_offsets(piece_type, PieceColor::BLACK).len(), 4);
+    assert!(registry.rider_offsets(piece_type, PieceColor::WHITE).is_empty());
+
+    // nothing outside the registry knows about it
+    assert_eq!(PieceType::from_letter('e'), PieceType::NULL);
+    assert_eq!(PieceRegistry::default().from_letter('e'), PieceType::NULL);
+    assert_eq!(piece_type.value(), 0);
+
+    // letters already in use and offsets that stay put are refused
+    assert!(registry.register(alfil.clone()).is_err());
+    assert!(registry.register(CustomPiece { letter: 'q', ..alfil.clone() }).is_err());
+    assert!(registry.register(CustomPiece { letter: '1', ..alfil.clone() }).is_err());
+    assert!(registry.register(CustomPiece { letter: 'y', leaper_offsets: vec![Vec2::ZERO], ..alfil }).is_err());
+}
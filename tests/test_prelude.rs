@@ -0,0 +1,10 @@
+use quasar::prelude::*;
+
+#[test]
+fn test_prelude() {
+    let state = State::from_fen(START_FEN.to_owned()).unwrap();
+    let knight: &Piece = state.get_piece_at(Vec2::new(2, 1)).unwrap();
+    assert_eq!(knight.get_piece_type(), PieceType::KNIGHT);
+    assert_eq!(Generator::new(knight.clone(), state.clone()).count(), 2);
+    assert_eq!(algebraic_to_position("b1"), Some(Vec2::new(2, 1)));
+}
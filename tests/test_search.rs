@@ -0,0 +1,91 @@
+use quasar::eval::MATE;
+use quasar::search::*;
+use quasar::state::*;
+use glam::IVec2 as Vec2;
+use std::time::{Duration, Instant};
+
+#[test]
+fn test_mate_in_one() {
+    let state = State::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1".to_owned()).unwrap();
+    let (piece_move, score) = best_move(&state, 2).unwrap();
+    assert_eq!(piece_move.end, Vec2::new(1, 8));
+    assert_eq!(score, MATE - 1);
+    assert!(state.make_move(piece_move).is_checkmate());
+}
+
+#[test]
+fn test_mate_in_one_for_black() {
+    let state = State::from_fen("r5k1/8/8/8/8/8/5PPP/6K1 b - - 0 1".to_owned()).unwrap();
+    let (piece_move, _) = best_move(&state, 1).unwrap();
+    assert!(state.make_move(piece_move).is_checkmate());
+}
+
+#[test]
+fn test_prefers_mate_over_stalemate() {
+    // qb6 would leave black without a move, qb8 mates
+    let state = State::from_fen("k7/2K5/8/8/8/8/8/1Q6 w - - 0 1".to_owned()).unwrap();
+    let (piece_move, score) = best_move(&state, 1).unwrap();
+    assert_eq!(score, MATE - 1);
+    assert!(state.make_move(piece_move).is_checkmate());
+}
+
+#[test]
+fn test_wins_hanging_queen() {
+    let state = State::from_fen("4k3/8/8/3q4/8/8/8/3RK3 w - - 0 1".to_owned()).unwrap();
+    let (piece_move, score) = best_move(&state, 2).unwrap();
+    assert_eq!(piece_move.end, Vec2::new(4, 5));
+    assert!(score > 0);
+}
+
+#[test]
+fn test_no_move_without_depth() {
+    let state = State::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1".to_owned()).unwrap();
+    assert!(best_move(&state, 0).is_none());
+}
+
+#[test]
+fn test_order_moves() {
+    let state = State::from_fen("4k3/1P6/8/3q1r2/4P3/8/8/3QK3 w - - 0 1".to_owned()).unwrap();
+    let mut moves = state.get_legal_moves();
+    state.order_moves(&mut moves);
+
+    // pawn takes queen before queen takes queen before pawn takes rook
+    assert_eq!(moves[0].start, Vec2::new(5, 4));
+    assert_eq!(moves[0].end, Vec2::new(4, 5));
+    assert_eq!(moves[1].start, Vec2::new(4, 1));
+    assert_eq!(moves[1].end, Vec2::new(4, 5));
+    assert_eq!(moves[2].end, Vec2::new(6, 5));
+    // then the promotions, queen first
+    assert_eq!(moves[3].promotion, Some(quasar::pieces::PieceType::QUEEN));
+    assert!(moves[7..].iter().all(|piece_move| piece_move.target.is_none() && piece_move.promotion.is_none()));
+}
+
+#[test]
+fn test_ordering_reduces_nodes() {
+    let state = State::from_fen("r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 0 1".to_owned()).unwrap();
+    let mut ordered = Search::new();
+    let mut unordered = Search::new();
+    unordered.ordering = false;
+    let ordered_score = ordered.best_move(&state, 3).unwrap().1;
+    let unordered_score = unordered.best_move(&state, 3).unwrap().1;
+
+    assert_eq!(ordered_score, unordered_score);
+    assert!(ordered.nodes < unordered.nodes, "{} >= {}", ordered.nodes, unordered.nodes);
+}
+
+#[test]
+fn test_best_move_timed() {
+    let state = State::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1".to_owned()).unwrap();
+    let start = Instant::now();
+    let (piece_move, depth) = best_move_timed(&state, Duration::from_millis(200)).unwrap();
+    assert!(start.elapsed() < Duration::from_secs(2));
+    assert!(depth >= 2);
+    assert_eq!(piece_move.end, Vec2::new(1, 8));
+
+    // a zero budget still completes the first depth
+    let (_, depth) = best_move_timed(&state, Duration::ZERO).unwrap();
+    assert_eq!(depth, 1);
+
+    let stalemate = State::from_fen("k7/8/1Q6/8/8/8/8/7K b - - 0 1".to_owned()).unwrap();
+    assert!(best_move_timed(&stalemate, Duration::from_millis(10)).is_none());
+}
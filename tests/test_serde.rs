@@ -0,0 +1,52 @@
+#![cfg(feature = "serde")]
+
+use std::sync::Arc;
+
+use quasar::pieces::*;
+use quasar::state::*;
+
+#[test]
+fn test_state_round_trip() {
+    let state = State::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1".to_owned()).unwrap();
+    let json = serde_json::to_string(&state).unwrap();
+    let restored: State = serde_json::from_str(&json).unwrap();
+    assert_eq!(restored.to_fen(), state.to_fen());
+    assert_eq!(restored.hash(), state.hash());
+}
+
+#[test]
+fn test_restored_state_lookups() {
+    let state = State::default();
+    let restored: State = serde_json::from_str(&serde_json::to_string(&state).unwrap()).unwrap();
+    let piece_move = restored.legal_moves_from(glam::IVec2::new(5, 2)).remove(0);
+    let moved = restored.make_move(piece_move.clone());
+    assert!(moved.get_piece_at(piece_move.start).is_none());
+    assert_eq!(moved.get_piece_at(piece_move.end).unwrap().get_id(), piece_move.piece.get_id());
+}
+
+#[test]
+fn test_history_round_trip() {
+    let mut state = State::default();
+    for uci in ["g1f3", "g8f6", "f3g1", "f6g8", "e2e4"] {
+        state = state.apply_uci(uci).unwrap();
+    }
+    let restored: State = serde_json::from_str(&serde_json::to_string(&state).unwrap()).unwrap();
+    assert_eq!(restored.history(), state.history());
+    let mut restored = restored;
+    restored.unmake_move().unwrap();
+    assert_eq!(restored.repetition_count(), 2);
+}
+
+#[test]
+fn test_custom_pieces_round_trip() {
+    let mut registry = PieceRegistry::default();
+    let camel = registry.register(CustomPiece {
+        name: "Camel".to_owned(), letter: 'l', value: 250,
+        leaper_offsets: vec![glam::IVec2::new(1, 3), glam::IVec2::new(3, 1), glam::IVec2::new(-1, -3), glam::IVec2::new(-3, -1)], rider_offsets: vec![],
+    }).unwrap();
+    let state = State::from_fen_with_pieces("4k3/8/8/8/3L4/8/8/4K3 w - - 0 1".to_owned(), 8, 8, Arc::new(registry)).unwrap();
+    let restored: State = serde_json::from_str(&serde_json::to_string(&state).unwrap()).unwrap();
+    assert_eq!(restored.config.pieces.get(camel).unwrap().name, "Camel");
+    assert_eq!(restored.to_fen(), state.to_fen());
+    assert_eq!(restored.get_legal_moves(), state.get_legal_moves());
+}
@@ -0,0 +1,1099 @@
+use quasar::state::*;
+use quasar::pieces::{PieceColor, PieceType};
+use quasar::moves::{Generator, Move};
+use quasar::config::Config;
+use glam::IVec2 as Vec2;
+
+const START_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 0";
+
+#[test]
+fn test_to_fen_start_position() {
+    let state = State::from_fen(START_FEN.to_owned()).unwrap();
+    assert_eq!(state.to_fen(), START_FEN);
+}
+
+#[test]
+fn test_to_fen_round_trip() {
+    let fen = "r3k2r/pp1n1ppp/2p5/4P3/1b6/2N5/PPP2PPP/R3K2R b KQkq - 3 0";
+    let state = State::from_fen(fen.to_owned()).unwrap();
+    assert_eq!(State::from_fen(state.to_fen()).unwrap().to_fen(), fen);
+}
+
+#[test]
+fn test_to_fen_en_passant_target() {
+    let state = State::from_fen(START_FEN.to_owned()).unwrap();
+    let pawn = state.get_piece_at(Vec2::new(5, 2)).unwrap().clone();
+    let double_step = Move::new(Vec2::new(5, 2), Vec2::new(5, 4), pawn, None, None, false, None, false);
+    let state = state.make_move(double_step);
+
+    assert_eq!(state.get_en_passant_target(), Some(Vec2::new(5, 3)));
+    assert_eq!(state.to_fen(), "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 0");
+}
+
+#[test]
+fn test_castling_rights_from_fen() {
+    let state = State::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w Kq - 0 0".to_owned()).unwrap();
+    assert!(!state.get_piece_at(Vec2::new(8, 1)).unwrap().has_moved());
+    assert!(state.get_piece_at(Vec2::new(1, 1)).unwrap().has_moved());
+    assert!(!state.get_piece_at(Vec2::new(5, 1)).unwrap().has_moved());
+    assert!(state.get_piece_at(Vec2::new(8, 8)).unwrap().has_moved());
+    assert!(!state.get_piece_at(Vec2::new(1, 8)).unwrap().has_moved());
+    assert_eq!(state.to_fen(), "r3k2r/8/8/8/8/8/8/R3K2R w Kq - 0 0");
+}
+
+#[test]
+fn test_no_castling_rights_from_fen() {
+    let state = State::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w - - 0 0".to_owned()).unwrap();
+    assert!(state.get_piece_at(Vec2::new(5, 1)).unwrap().has_moved());
+    assert!(state.get_piece_at(Vec2::new(5, 8)).unwrap().has_moved());
+    assert_eq!(state.to_fen(), "r3k2r/8/8/8/8/8/8/R3K2R w - - 0 0");
+}
+
+#[test]
+fn test_malformed_castling_rights() {
+    assert!(State::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KX - 0 0".to_owned()).is_err());
+    assert!(State::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KK - 0 0".to_owned()).is_err());
+}
+
+fn castling_moves(state: &State, king_pos: Vec2) -> Vec<Move> {
+    let king = state.get_piece_at(king_pos).unwrap().clone();
+    let mut gen = Generator::new(king, state.clone());
+    let mut moves = vec![];
+    for _ in 0..32 {
+        if let Some(piece_move) = gen.next_pseudo() {
+            if piece_move.castling {
+                moves.push(piece_move);
+            }
+        }
+    }
+    moves
+}
+
+#[test]
+fn test_make_castling_move() {
+    let state = State::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 0".to_owned()).unwrap();
+    let moves = castling_moves(&state, Vec2::new(5, 1));
+    assert_eq!(moves.len(), 2);
+
+    let kingside = moves.iter().find(|piece_move| piece_move.end.x > piece_move.start.x).unwrap();
+    let after = state.clone().make_move(kingside.clone());
+    let king = after.get_piece_at(Vec2::new(7, 1)).unwrap();
+    let rook = after.get_piece_at(Vec2::new(6, 1)).unwrap();
+    assert_eq!(king.get_piece_type(), PieceType::KING);
+    assert_eq!(rook.get_piece_type(), PieceType::ROOK);
+    assert!(king.has_moved() && rook.has_moved());
+    assert!(after.get_piece_at(Vec2::new(5, 1)).is_none());
+    assert!(after.get_piece_at(Vec2::new(8, 1)).is_none());
+
+    let queenside = moves.iter().find(|piece_move| piece_move.end.x < piece_move.start.x).unwrap();
+    let after = state.make_move(queenside.clone());
+    assert_eq!(after.get_piece_at(Vec2::new(3, 1)).unwrap().get_piece_type(), PieceType::KING);
+    assert_eq!(after.get_piece_at(Vec2::new(4, 1)).unwrap().get_piece_type(), PieceType::ROOK);
+    assert!(after.get_piece_at(Vec2::new(1, 1)).is_none());
+}
+
+#[test]
+fn test_no_castling_through_check() {
+    // the rook covers a square the king crosses, lands on or starts from
+    for fen in ["4kr2/8/8/8/8/8/8/4K2R w K - 0 1", "4k1r1/8/8/8/8/8/8/4K2R w K - 0 1", "4r2k/8/8/8/8/8/8/4K2R w K - 0 1", "3rk3/8/8/8/8/8/8/R3K3 w Q - 0 1"] {
+        let state = State::from_fen(fen.to_owned()).unwrap();
+        assert!(castling_moves(&state, Vec2::new(5, 1)).is_empty(), "{}", fen);
+    }
+    let state = State::from_fen("4k3/8/8/8/8/8/8/R3K2R w KQ - 0 1".to_owned()).unwrap();
+    assert_eq!(castling_moves(&state, Vec2::new(5, 1)).len(), 2);
+    // the queenside rook passes b1, the king does not
+    let state = State::from_fen("1r2k3/8/8/8/8/8/8/R3K3 w Q - 0 1".to_owned()).unwrap();
+    assert_eq!(castling_moves(&state, Vec2::new(5, 1)).len(), 1);
+}
+
+#[test]
+fn test_en_passant_and_fullmove_from_fen() {
+    let fen = "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3";
+    let state = State::from_fen(fen.to_owned()).unwrap();
+    assert_eq!(state.get_en_passant_target(), Some(Vec2::new(6, 6)));
+    assert_eq!(state.full_moves, 3);
+    assert_eq!(state.to_fen(), fen);
+}
+
+#[test]
+fn test_invalid_en_passant_square() {
+    assert!(State::from_fen("4k3/8/8/8/8/8/8/4K3 w - e6 0 1".to_owned()).is_err());
+    assert!(State::from_fen("4k3/8/8/8/8/8/8/4K3 w - 9 0 1".to_owned()).is_err());
+}
+
+#[test]
+fn test_make_en_passant_move() {
+    let state = State::from_fen("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3".to_owned()).unwrap();
+    let pawn = state.get_piece_at(Vec2::new(5, 5)).unwrap().clone();
+    let en_passant = Move::new(Vec2::new(5, 5), Vec2::new(6, 6), pawn, None, None, false, None, true);
+    let state = state.make_move(en_passant);
+
+    assert_eq!(state.get_piece_at(Vec2::new(6, 6)).unwrap().get_color(), PieceColor::WHITE);
+    assert!(state.get_piece_at(Vec2::new(6, 5)).is_none());
+    assert!(state.get_piece_at(Vec2::new(4, 5)).unwrap().is_alive());
+}
+
+fn diff_squares(before: &State, after: &State) -> Vec<Vec2> {
+    before.diff(after).into_iter().map(|(square, _)| square).collect()
+}
+
+#[test]
+fn test_apply_uci_and_diff() {
+    let state = State::default();
+    let after = state.apply_uci("e2e4").unwrap();
+    let diff = state.diff(&after);
+    assert_eq!(diff.len(), 2);
+    assert_eq!(diff[0], (Vec2::new(5, 2), None));
+    assert_eq!(diff[1].0, Vec2::new(5, 4));
+    assert_eq!(diff[1].1.as_ref().unwrap().get_piece_type(), PieceType::PAWN);
+    assert!(state.diff(&state).is_empty());
+
+    let state = State::from_fen("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3".to_owned()).unwrap();
+    let after = state.apply_uci("e5f6").unwrap();
+    assert_eq!(diff_squares(&state, &after), vec![Vec2::new(5, 5), Vec2::new(6, 5), Vec2::new(6, 6)]);
+    let after = after.apply_uci("e7f6").unwrap();
+    assert_eq!(after.get_piece_at(Vec2::new(6, 6)).unwrap().get_color(), PieceColor::BLACK);
+
+    let state = State::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1".to_owned()).unwrap();
+    let castled = state.apply_uci("e1g1").unwrap();
+    assert_eq!(diff_squares(&state, &castled), vec![Vec2::new(5, 1), Vec2::new(6, 1), Vec2::new(7, 1), Vec2::new(8, 1)]);
+    assert_eq!(state.apply_uci("e1h1").unwrap(), castled);
+}
+
+#[test]
+fn test_castling_uci_round_trip() {
+    let state = State::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1".to_owned()).unwrap();
+    let mut castling: Vec<Move> = state.legal_moves_from(Vec2::new(5, 1)).into_iter().filter(|piece_move| piece_move.castling).collect();
+    castling.sort();
+    let uci: Vec<String> = castling.iter().map(|piece_move| piece_move.to_uci(&state).unwrap()).collect();
+    assert_eq!(uci, vec!["e1c1", "e1g1"]);
+    for (piece_move, uci) in castling.iter().zip(uci.iter()) {
+        assert_eq!(&state.parse_uci(uci).unwrap(), piece_move);
+    }
+
+    // the king on b1 could also step to c1, so castling with the a1 rook keeps the rook's square
+    let state = State::from_fen("rk5r/8/8/8/8/8/8/RK5R w KQkq - 0 1".to_owned()).unwrap();
+    let mut castling: Vec<Move> = state.legal_moves_from(Vec2::new(2, 1)).into_iter().filter(|piece_move| piece_move.castling).collect();
+    castling.sort();
+    let uci: Vec<String> = castling.iter().map(|piece_move| piece_move.to_uci(&state).unwrap()).collect();
+    assert_eq!(uci, vec!["b1a1", "b1g1"]);
+    for (piece_move, uci) in castling.iter().zip(uci.iter()) {
+        assert_eq!(&state.parse_uci(uci).unwrap(), piece_move);
+    }
+    assert!(!state.parse_uci("b1c1").unwrap().castling);
+}
+
+#[test]
+fn test_apply_uci_promotion_and_errors() {
+    let state = State::from_fen("4k3/P7/8/8/8/8/8/4K3 w - - 0 1".to_owned()).unwrap();
+    let promotion = state.legal_moves_from(Vec2::new(1, 7)).into_iter().find(|piece_move| piece_move.promotion == Some(PieceType::KNIGHT)).unwrap();
+    assert_eq!(promotion.to_uci(&state).unwrap(), "a7a8n");
+    assert!(state.apply_uci("a7a8n").is_ok());
+    assert!(state.apply_uci("a7a8").is_err());
+    assert!(state.apply_uci("a7a8x").is_err());
+    assert!(state.apply_uci("a7a8qq").is_err());
+    assert!(state.apply_uci("e1e3").is_err());
+    assert!(state.apply_uci("e2").is_err());
+    assert!(state.apply_uci("").is_err());
+}
+
+#[test]
+fn test_parse_uci() {
+    let state = State::from_fen("r3k2r/8/8/3pP3/8/8/8/R3K2R w KQkq d6 0 1".to_owned()).unwrap();
+    let en_passant = state.parse_uci("e5d6").unwrap();
+    assert!(en_passant.en_passant);
+    assert_eq!(en_passant.piece.get_piece_type(), PieceType::PAWN);
+    assert!(state.parse_uci("e1g1").unwrap().castling);
+    assert!(!state.parse_uci("e1f1").unwrap().castling);
+
+    assert_eq!(state.parse_uci("e1").unwrap_err(), "Invalid uci move: e1");
+    assert_eq!(state.parse_uci("E1G1").unwrap_err(), "Invalid uci move: E1G1");
+    assert_eq!(state.parse_uci("e1i1").unwrap_err(), "Square [9, 1] of uci move e1i1 is off the board.");
+    assert_eq!(state.parse_uci("e1e9").unwrap_err(), "Square [5, 9] of uci move e1e9 is off the board.");
+    assert_eq!(state.parse_uci("e1e3").unwrap_err(), "Move e1e3 is not legal.");
+    // black's pieces cannot move on white's turn
+    assert_eq!(state.parse_uci("e8g8").unwrap_err(), "Move e8g8 is not legal.");
+}
+
+#[test]
+fn test_is_square_attacked() {
+    let state = State::from_fen("4k3/8/8/3p4/8/1n6/8/R3K2B w - - 0 1".to_owned()).unwrap();
+
+    // pawn attacks diagonally forward only
+    assert!(state.is_square_attacked(Vec2::new(3, 4), PieceColor::BLACK));
+    assert!(state.is_square_attacked(Vec2::new(5, 4), PieceColor::BLACK));
+    assert!(!state.is_square_attacked(Vec2::new(3, 6), PieceColor::BLACK));
+
+    // knight jumps
+    assert!(state.is_square_attacked(Vec2::new(4, 2), PieceColor::BLACK));
+    assert!(state.is_square_attacked(Vec2::new(1, 1), PieceColor::BLACK));
+
+    // rook ray stops at the king
+    assert!(state.is_square_attacked(Vec2::new(4, 1), PieceColor::WHITE));
+    assert!(state.is_square_attacked(Vec2::new(1, 8), PieceColor::WHITE));
+    assert!(!state.is_square_attacked(Vec2::new(7, 1), PieceColor::WHITE));
+
+    // bishop ray blocked by the pawn
+    assert!(state.is_square_attacked(Vec2::new(5, 4), PieceColor::WHITE));
+    assert!(state.is_square_attacked(Vec2::new(4, 5), PieceColor::WHITE));
+    assert!(!state.is_square_attacked(Vec2::new(3, 6), PieceColor::WHITE));
+
+    // king adjacency
+    assert!(state.is_square_attacked(Vec2::new(4, 7), PieceColor::BLACK));
+    assert!(!state.is_square_attacked(Vec2::new(5, 6), PieceColor::BLACK));
+}
+
+#[test]
+fn test_ray_attacks() {
+    let state = State::from_fen("4k3/8/8/8/1p6/8/8/R2NK3 w - - 0 1".to_owned()).unwrap();
+    let up = state.ray_attacks(Vec2::new(1, 1), Vec2::new(0, 1));
+    assert_eq!(up.len(), 7);
+    let right = state.ray_attacks(Vec2::new(1, 1), Vec2::new(1, 0));
+    assert_eq!(right, vec![Vec2::new(2, 1), Vec2::new(3, 1)]);
+    let diagonal = state.ray_attacks(Vec2::new(1, 1), Vec2::new(1, 1));
+    assert_eq!(diagonal, vec![Vec2::new(2, 2), Vec2::new(3, 3), Vec2::new(4, 4), Vec2::new(5, 5), Vec2::new(6, 6), Vec2::new(7, 7), Vec2::new(8, 8)]);
+    let blocked = state.ray_attacks(Vec2::new(4, 1), Vec2::new(-1, 0));
+    assert_eq!(blocked, vec![Vec2::new(3, 1), Vec2::new(2, 1)]);
+    let capture = state.ray_attacks(Vec2::new(4, 1), Vec2::new(-1, 1));
+    assert_eq!(capture, vec![Vec2::new(3, 2), Vec2::new(2, 3), Vec2::new(1, 4)]);
+}
+
+#[test]
+fn test_sliding_generator_stops_at_blockers() {
+    let state = State::from_fen("4k3/8/8/8/p7/8/8/R2NK3 w - - 0 1".to_owned()).unwrap();
+    let rook = state.get_piece_at(Vec2::new(1, 1)).unwrap().clone();
+    let mut gen = Generator::new(rook, state);
+    let mut ends = vec![];
+    for _ in 0..32 {
+        if let Some(piece_move) = gen.next_pseudo() {
+            ends.push(piece_move.end);
+        }
+    }
+    ends.sort_by_key(|end| (end.x, end.y));
+    assert_eq!(ends, vec![Vec2::new(1, 2), Vec2::new(1, 3), Vec2::new(1, 4), Vec2::new(2, 1), Vec2::new(3, 1)]);
+}
+
+fn pseudo_moves(state: &State, pos: Vec2) -> Vec<Move> {
+    let piece = state.get_piece_at(pos).unwrap().clone();
+    let mut gen = Generator::new(piece, state.clone());
+    let mut moves = vec![];
+    for _ in 0..64 {
+        if let Some(piece_move) = gen.next_pseudo() {
+            moves.push(piece_move);
+        }
+    }
+    moves
+}
+
+#[test]
+fn test_generate_en_passant() {
+    let state = State::from_fen("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3".to_owned()).unwrap();
+    let moves = pseudo_moves(&state, Vec2::new(5, 5));
+    let diagonal: Vec<&Move> = moves.iter().filter(|piece_move| piece_move.end.x != piece_move.start.x).collect();
+    assert_eq!(diagonal.len(), 1);
+    assert!(diagonal[0].en_passant);
+    assert_eq!(diagonal[0].end, Vec2::new(6, 6));
+
+    let state = state.make_move(diagonal[0].clone());
+    assert!(state.get_piece_at(Vec2::new(6, 5)).is_none());
+}
+
+#[test]
+fn test_no_en_passant_without_double_step() {
+    let state = State::from_fen("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq - 0 3".to_owned()).unwrap();
+    let moves = pseudo_moves(&state, Vec2::new(5, 5));
+    assert!(moves.iter().all(|piece_move| piece_move.end.x == piece_move.start.x));
+}
+
+#[test]
+fn test_en_passant_square_lasts_one_move() {
+    let mut state = State::from_fen("4k3/3p4/8/4P3/8/8/8/4K3 b - - 0 1".to_owned()).unwrap();
+    let double_step = pseudo_moves(&state, Vec2::new(4, 7)).into_iter().find(|piece_move| piece_move.end == Vec2::new(4, 5)).unwrap();
+    state = state.make_move(double_step);
+    assert_eq!(state.get_en_passant_target(), Some(Vec2::new(4, 6)));
+
+    // the target belongs to the position, not to the recorded last move
+    state.previous_move = None;
+    assert_eq!(pseudo_moves(&state, Vec2::new(5, 5)).iter().filter(|piece_move| piece_move.en_passant).count(), 1);
+
+    let king_move = pseudo_moves(&state, Vec2::new(5, 1)).into_iter().next().unwrap();
+    let state = state.make_move(king_move);
+    assert_eq!(state.get_en_passant_target(), None);
+}
+
+#[test]
+fn test_history_and_replay() {
+    let mut state = State::default();
+    for (start, end) in [((5, 2), (5, 4)), ((5, 7), (5, 5)), ((7, 1), (6, 3)), ((2, 8), (3, 6))] {
+        let piece_move = state.legal_moves_from(Vec2::new(start.0, start.1)).into_iter()
+            .find(|piece_move| piece_move.end == Vec2::new(end.0, end.1))
+            .unwrap();
+        state = state.make_move(piece_move);
+    }
+    let history = state.history();
+    assert_eq!(history.len(), 4);
+    assert_eq!(history[0].end, Vec2::new(5, 4));
+    assert_eq!(history[3].end, Vec2::new(3, 6));
+    assert!(State::default().history().is_empty());
+
+    let replayed = State::replay(&history).unwrap();
+    assert_eq!(replayed, state);
+    assert_eq!(replayed.to_fen(), state.to_fen());
+    assert_eq!(replayed.history(), history);
+
+    let error = State::replay(&history[1..]).unwrap_err();
+    assert!(error.contains("Move 1"), "{}", error);
+}
+
+#[test]
+fn test_history_is_shared() {
+    let state = State::default().apply_uci("e2e4").unwrap();
+    let branch = state.clone().apply_uci("e7e5").unwrap();
+    let other = state.clone().apply_uci("c7c5").unwrap();
+    assert_eq!(state.history().len(), 1);
+    assert_eq!(branch.history()[0], other.history()[0]);
+    assert_eq!(branch.history()[1].end, Vec2::new(5, 5));
+    assert_eq!(other.history()[1].end, Vec2::new(3, 5));
+
+    // looking ahead keeps nothing to take back
+    let piece_move = state.get_legal_moves().remove(0);
+    let simulated = state.clone().simulate_move(piece_move.clone());
+    assert!(simulated.history().is_empty());
+    assert!(simulated.clone().unmake_move().is_err());
+    assert_eq!(state.clone().simulate_move(piece_move.clone()), state.make_move(piece_move));
+}
+
+#[test]
+fn test_is_move_legal() {
+    let state = State::default();
+    assert!(state.is_move_legal(Vec2::new(5, 2), Vec2::new(5, 4), None));
+    assert!(state.is_move_legal(Vec2::new(7, 1), Vec2::new(6, 3), None));
+    assert!(!state.is_move_legal(Vec2::new(5, 2), Vec2::new(5, 5), None));
+    assert!(!state.is_move_legal(Vec2::new(5, 7), Vec2::new(5, 5), None));
+    assert!(!state.is_move_legal(Vec2::new(4, 4), Vec2::new(4, 5), None));
+
+    let state = State::from_fen("4k3/1P6/8/8/8/8/8/R3K3 w Q - 0 1".to_owned()).unwrap();
+    assert!(state.is_move_legal(Vec2::new(2, 7), Vec2::new(2, 8), Some(PieceType::QUEEN)));
+    assert!(!state.is_move_legal(Vec2::new(2, 7), Vec2::new(2, 8), None));
+    assert!(!state.is_move_legal(Vec2::new(2, 7), Vec2::new(2, 8), Some(PieceType::KING)));
+    // castling by the king's landing square or by the rook's square
+    assert!(state.is_move_legal(Vec2::new(5, 1), Vec2::new(3, 1), None));
+    assert!(state.is_move_legal(Vec2::new(5, 1), Vec2::new(1, 1), None));
+    assert!(!state.is_move_legal(Vec2::new(5, 1), Vec2::new(7, 1), None));
+}
+
+#[test]
+fn test_unmake_move() {
+    let fens = [
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+        "4k3/1P6/8/8/8/8/8/4K3 w - - 7 12",
+        "r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 3 20",
+    ];
+    for fen in fens {
+        let state = State::from_fen(fen.to_owned()).unwrap();
+        for piece_move in state.get_legal_moves() {
+            let mut undone = state.clone().make_move(piece_move.clone());
+            assert_eq!(undone.unmake_move().unwrap(), Some(piece_move.clone()));
+            assert_eq!(undone.get_pieces(), state.get_pieces(), "{:?}", piece_move);
+            assert_eq!(undone.full_moves, state.full_moves);
+            assert_eq!(undone.get_en_passant_target(), state.get_en_passant_target());
+            assert_eq!(undone.to_fen(), state.to_fen());
+            assert!(undone.history().is_empty());
+        }
+    }
+    assert!(State::default().unmake_move().is_err());
+
+    // a capture two moves back comes back too
+    let state = State::from_fen("4k3/8/8/3p4/4P3/8/8/4K3 w - - 0 1".to_owned()).unwrap();
+    let capture = state.legal_moves_from(Vec2::new(5, 4)).into_iter().find(|piece_move| piece_move.end == Vec2::new(4, 5)).unwrap();
+    let after = state.clone().make_move(capture);
+    let king_move = after.legal_moves_from(Vec2::new(5, 8)).into_iter().next().unwrap();
+    let after = after.make_move(king_move);
+    assert_eq!(after.get_pieces().iter().filter(|piece| piece.is_alive()).count(), 3);
+    let mut undone = after;
+    undone.unmake_move().unwrap();
+    undone.unmake_move().unwrap();
+    assert_eq!(undone.get_pieces(), state.get_pieces());
+}
+
+#[test]
+fn test_bounding_box_and_occupied_squares() {
+    let state = State::from_fen("4k3/8/8/3p4/4P3/8/8/4K3 w - - 0 1".to_owned()).unwrap();
+    assert_eq!(state.bounding_box(), (Vec2::new(1, 1), Vec2::new(8, 8)));
+    assert_eq!(state.occupied_squares().count(), 4);
+
+    let capture = state.legal_moves_from(Vec2::new(5, 4)).into_iter().find(|piece_move| piece_move.end == Vec2::new(4, 5)).unwrap();
+    let state = state.make_move(capture);
+    let occupied: Vec<(Vec2, PieceType)> = state.occupied_squares().map(|(square, piece)| (square, piece.get_piece_type())).collect();
+    assert_eq!(occupied.len(), 3);
+    assert!(occupied.contains(&(Vec2::new(4, 5), PieceType::PAWN)));
+
+    let state = StateBuilder::new()
+        .place(PieceType::KING, PieceColor::WHITE, Vec2::new(-3, 2))
+        .place(PieceType::KING, PieceColor::BLACK, Vec2::new(4, -1))
+        .place(PieceType::QUEEN, PieceColor::BLACK, Vec2::new(0, 0))
+        .build()
+        .unwrap();
+    assert_eq!(state.bounding_box(), (Vec2::new(-3, -1), Vec2::new(4, 2)));
+    assert_eq!(state.to_string(), "K.......\n........\n...q....\n.......k\n");
+}
+
+#[test]
+fn test_display_after_capture() {
+    // the captured pawn was listed first and used to hide the knight standing on its square
+    let state = State::from_fen("4k3/8/8/3p4/8/4N3/8/4K3 w - - 0 1".to_owned()).unwrap();
+    let capture = state.legal_moves_from(Vec2::new(5, 3)).into_iter().find(|piece_move| piece_move.end == Vec2::new(4, 5)).unwrap();
+    let state = state.make_move(capture);
+    assert_eq!(state.get_piece_at(Vec2::new(4, 5)).unwrap().get_piece_type(), PieceType::KNIGHT);
+    assert_eq!(state.to_string(), "....k...\n........\n........\n...N....\n........\n........\n........\n....K...\n");
+    assert_eq!(state.to_fen(), "4k3/8/8/3N4/8/8/8/4K3 b - - 0 1");
+    assert!(state.get_piece_at(Vec2::new(5, 3)).is_none());
+}
+
+#[test]
+fn test_null_move() {
+    let state = State::from_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1".to_owned()).unwrap();
+    let passed = state.make_null_move().unwrap();
+    assert_eq!(passed.to_move, PieceColor::WHITE);
+    assert_eq!(passed.get_en_passant_target(), None);
+    assert_eq!(passed.to_fen(), "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 1 2");
+    assert_eq!(passed.get_pieces(), state.get_pieces());
+
+    let in_check = State::from_fen("4k3/8/8/8/8/8/8/r3K3 w - - 0 1".to_owned()).unwrap();
+    assert!(in_check.make_null_move().is_err());
+
+    // taking the pass back leaves the real move before it in place
+    let state = State::default().apply_uci("e2e4").unwrap();
+    let mut passed = state.make_null_move().unwrap();
+    assert_eq!(passed.history().len(), 1);
+    assert_eq!(passed.unmake_move().unwrap(), None);
+    assert_eq!(passed, state);
+    assert_eq!(passed.to_fen(), state.to_fen());
+    assert_eq!(passed.history(), state.history());
+    assert!(passed.unmake_move().unwrap().is_some());
+    assert_eq!(passed, State::default());
+
+    // passing counts towards repetitions like any other reversible move
+    let twice = State::default().make_null_move().unwrap().make_null_move().unwrap();
+    assert_eq!(twice.repetition_count(), 2);
+}
+
+#[test]
+fn test_state_builder() {
+    let state = StateBuilder::new()
+        .place(PieceType::KING, PieceColor::WHITE, Vec2::new(1000, 1000))
+        .place(PieceType::KING, PieceColor::BLACK, Vec2::new(-1000, 1000))
+        .place(PieceType::ROOK, PieceColor::BLACK, Vec2::new(1000, -5000))
+        .to_move(PieceColor::BLACK)
+        .build()
+        .unwrap();
+    assert_eq!(state.to_move, PieceColor::BLACK);
+    assert_eq!(state.get_piece_at(Vec2::new(1000, -5000)).unwrap().get_piece_type(), PieceType::ROOK);
+    assert!(state.is_king_in_check(PieceColor::WHITE));
+    assert!(!state.is_king_in_check(PieceColor::BLACK));
+}
+
+#[test]
+fn test_state_builder_requires_kings() {
+    let builder = StateBuilder::new().place(PieceType::KING, PieceColor::WHITE, Vec2::ZERO);
+    assert!(builder.clone().build().is_err());
+    assert!(builder.clone().allow_no_king().build().is_ok());
+    assert!(builder.place(PieceType::PAWN, PieceColor::BLACK, Vec2::ZERO).allow_no_king().build().is_err());
+}
+
+#[test]
+fn test_get_legal_moves() {
+    let state = State::from_fen(START_FEN.to_owned()).unwrap();
+    assert_eq!(state.get_legal_moves().len(), 20);
+    assert_eq!(state.legal_moves_iter().count(), 20);
+
+    let state = State::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 0".to_owned()).unwrap();
+    let castling = state.legal_moves_iter().filter(|piece_move| piece_move.castling).count();
+    assert_eq!(castling, 2);
+    assert_eq!(state.get_legal_moves().len(), 26);
+}
+
+#[test]
+fn test_pinned_pieces() {
+    // knight pinned on the file, bishop pinned on the diagonal, pawn shielded together with the other knight is free
+    let state = State::from_fen("4r2k/8/8/b7/8/2B1N3/8/4KPNq w - - 0 1".to_owned()).unwrap();
+    let mut pinned = state.pinned_pieces(PieceColor::WHITE);
+    pinned.sort_by_key(|(square, _)| (square.x, square.y));
+    assert_eq!(pinned, vec![(Vec2::new(3, 3), Vec2::new(1, -1)), (Vec2::new(5, 3), Vec2::new(0, -1))]);
+    assert!(state.pinned_pieces(PieceColor::BLACK).is_empty());
+
+    let state = StateBuilder::new()
+        .place(PieceType::KING, PieceColor::WHITE, Vec2::ZERO)
+        .place(PieceType::ROOK, PieceColor::WHITE, Vec2::new(0, 500))
+        .place(PieceType::QUEEN, PieceColor::BLACK, Vec2::new(0, 100_000))
+        .place(PieceType::KING, PieceColor::BLACK, Vec2::new(7, 3))
+        .build()
+        .unwrap();
+    assert_eq!(state.pinned_pieces(PieceColor::WHITE), vec![(Vec2::new(0, 500), Vec2::new(0, -1))]);
+}
+
+#[test]
+fn test_default_state() {
+    let state = State::try_default().unwrap();
+    assert_eq!(state.to_fen(), quasar::state::START_FEN);
+    assert_eq!(State::default().to_fen(), state.to_fen());
+}
+
+#[test]
+fn test_from_start_fen() {
+    let state = State::from_start_fen(quasar::positions::KIWIPETE).unwrap();
+    assert_eq!(state.to_fen(), quasar::positions::KIWIPETE);
+    assert_eq!(State::from_start_fen(quasar::positions::START).unwrap(), State::default());
+    assert!(State::from_start_fen("not a fen").is_err());
+}
+
+#[test]
+fn test_try_make_move_rejects_missing_piece() {
+    let state = State::default();
+    let piece_move = state.get_legal_moves().remove(0);
+    let moved = state.clone().try_make_move(piece_move.clone()).unwrap();
+    // the piece is no longer where the move expects it
+    assert!(moved.try_make_move(piece_move).is_err());
+}
+
+#[test]
+fn test_state_equality() {
+    let start = State::default();
+    let mut state = start.clone();
+    for (from, to) in [((7, 1), (6, 3)), ((7, 8), (6, 6)), ((6, 3), (7, 1)), ((6, 6), (7, 8))] {
+        let piece_move = state.legal_moves_iter()
+            .find(|piece_move| (piece_move.start == Vec2::new(from.0, from.1)) && (piece_move.end == Vec2::new(to.0, to.1)))
+            .unwrap();
+        state = state.make_move(piece_move);
+    }
+    assert_eq!(state, start);
+    assert_ne!(state.full_moves, start.full_moves);
+
+    let mut seen = std::collections::HashSet::new();
+    seen.insert(start.clone());
+    assert!(seen.contains(&state));
+
+    // same placement, different en passant target
+    let pushed = State::from_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1".to_owned()).unwrap();
+    let quiet = State::from_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1".to_owned()).unwrap();
+    assert_ne!(pushed, quiet);
+    assert_ne!(quiet, start);
+}
+
+#[test]
+fn test_chess960() {
+    assert_eq!(State::chess960(518).unwrap().to_fen(), START_FEN.replace("0 0", "0 1"));
+    assert_eq!(State::chess960(0).unwrap().to_fen(), "bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w KQkq - 0 1");
+    assert!(State::chess960(960).is_err());
+
+    // clear the back rank between the king on b1 and its rooks on a1 and h1
+    let state = State::from_fen("rk5r/8/8/8/8/8/8/RK5R w KQkq - 0 1".to_owned()).unwrap();
+    let castling = castling_moves(&state, Vec2::new(2, 1));
+    assert_eq!(castling.len(), 2);
+
+    let kingside = castling.iter().find(|piece_move| piece_move.end == Vec2::new(8, 1)).unwrap().clone();
+    let state_after = state.clone().make_move(kingside);
+    assert_eq!(state_after.get_piece_at(Vec2::new(7, 1)).unwrap().get_piece_type(), PieceType::KING);
+    assert_eq!(state_after.get_piece_at(Vec2::new(6, 1)).unwrap().get_piece_type(), PieceType::ROOK);
+
+    // the rook on a1 crosses b1 and c1 only, the king goes from b1 to c1
+    let queenside = castling.iter().find(|piece_move| piece_move.end == Vec2::new(1, 1)).unwrap().clone();
+    let state_after = state.make_move(queenside);
+    assert_eq!(state_after.to_fen(), "rk5r/8/8/8/8/8/8/2KR3R b kq - 1 1");
+}
+
+#[test]
+fn test_chess960_castling_blocked() {
+    // the king on g1 stays put but the rook on b1 must pass the knight on c1
+    let state = State::from_fen("4k3/8/8/8/8/8/8/1RN3K1 w Q - 0 1".to_owned()).unwrap();
+    assert!(castling_moves(&state, Vec2::new(7, 1)).is_empty());
+    let state = State::from_fen("4k3/8/8/8/8/8/8/1R4K1 w Q - 0 1".to_owned()).unwrap();
+    let state_after = state.clone().make_move(castling_moves(&state, Vec2::new(7, 1)).remove(0));
+    assert_eq!(state_after.get_piece_at(Vec2::new(3, 1)).unwrap().get_piece_type(), PieceType::KING);
+    assert_eq!(state_after.get_piece_at(Vec2::new(4, 1)).unwrap().get_piece_type(), PieceType::ROOK);
+}
+
+#[test]
+fn test_castling_on_shifted_board() {
+    let shift = Vec2::new(1000, 1000);
+    let original = State::from_fen("r3k2r/pppppppp/8/8/8/8/PPPPPPPP/R3K2R w KQkq - 0 1".to_owned()).unwrap();
+    let mut builder = StateBuilder::new()
+        .config(Config::new([Vec2::new(0, 9) + shift, Vec2::new(9, 0) + shift], vec![1001, 1008]));
+    for piece in original.get_pieces() {
+        builder = builder.place(piece.get_piece_type(), piece.get_color(), *piece.get_position() + shift);
+    }
+    let state = builder.build().unwrap();
+
+    let castling = castling_moves(&state, Vec2::new(5, 1) + shift);
+    assert_eq!(castling.len(), 2);
+    for piece_move in castling {
+        let (king_end, rook_end) = match piece_move.end.x - shift.x {
+            8 => (Vec2::new(7, 1), Vec2::new(6, 1)),
+            _ => (Vec2::new(3, 1), Vec2::new(4, 1)),
+        };
+        let state_after = state.clone().make_move(piece_move);
+        assert_eq!(state_after.get_piece_at(king_end + shift).unwrap().get_piece_type(), PieceType::KING);
+        assert_eq!(state_after.get_piece_at(rook_end + shift).unwrap().get_piece_type(), PieceType::ROOK);
+    }
+}
+
+#[test]
+fn test_toroidal_board() {
+    let state = StateBuilder::new()
+        .config(Config::toroidal(8, 8, vec![]))
+        .place(PieceType::ROOK, PieceColor::WHITE, Vec2::new(1, 4))
+        .place(PieceType::KNIGHT, PieceColor::WHITE, Vec2::new(2, 4))
+        .place(PieceType::KING, PieceColor::WHITE, Vec2::new(3, 7))
+        .place(PieceType::KING, PieceColor::BLACK, Vec2::new(6, 2))
+        .build()
+        .unwrap();
+    assert_eq!(state.displacement(Vec2::new(1, 4), Vec2::new(8, 4)), Vec2::new(-1, 0));
+
+    // the rook on the a file reaches the h file across the edge, the knight stops it going right
+    assert!(state.is_square_attacked(Vec2::new(8, 4), PieceColor::WHITE));
+    assert!(state.is_square_attacked(Vec2::new(3, 4), PieceColor::WHITE));
+    let mut ends: Vec<i32> = pseudo_moves(&state, Vec2::new(1, 4)).iter()
+        .filter(|piece_move| piece_move.end.y == 4)
+        .map(|piece_move| piece_move.end.x)
+        .collect();
+    ends.sort();
+    assert_eq!(ends, vec![3, 4, 5, 6, 7, 8]);
+
+    // knights and kings wrap as well, the knight on h1 checks a3 and the king on a8 guards b1 through the corner
+    let state = StateBuilder::new()
+        .config(Config::toroidal(8, 8, vec![]))
+        .place(PieceType::KNIGHT, PieceColor::WHITE, Vec2::new(8, 1))
+        .place(PieceType::KING, PieceColor::WHITE, Vec2::new(1, 8))
+        .place(PieceType::KING, PieceColor::BLACK, Vec2::new(1, 3))
+        .to_move(PieceColor::BLACK)
+        .build()
+        .unwrap();
+    assert!(state.is_king_in_check(PieceColor::BLACK));
+    assert!(state.is_square_attacked(Vec2::new(2, 1), PieceColor::WHITE));
+    assert!(state.get_legal_moves().iter().all(|piece_move| !state.clone().make_move(piece_move.clone()).is_king_in_check(PieceColor::BLACK)));
+}
+
+#[test]
+fn test_piece_ids() {
+    let state = State::default();
+    let ids: Vec<u32> = state.get_pieces().iter().map(|piece| piece.get_id()).collect();
+    assert_eq!(ids, (0..32).collect::<Vec<u32>>());
+    // scan order starts at a8
+    assert_eq!(state.get_piece_at(Vec2::new(1, 8)).unwrap().get_id(), 0);
+
+    // the knight keeps its id across moves
+    let knight = state.get_piece_at(Vec2::new(7, 1)).unwrap().clone();
+    let piece_move = state.legal_moves_iter().find(|piece_move| piece_move.start == Vec2::new(7, 1) && piece_move.end == Vec2::new(6, 3)).unwrap();
+    let state_after = state.make_move(piece_move);
+    assert_eq!(state_after.get_piece_at(Vec2::new(6, 3)).unwrap().get_id(), knight.get_id());
+}
+
+#[test]
+fn test_identical_pieces_are_told_apart() {
+    // two unmoved rooks compare equal apart from id and square
+    let state = StateBuilder::new()
+        .place(PieceType::KING, PieceColor::WHITE, Vec2::new(5, 1))
+        .place(PieceType::KING, PieceColor::BLACK, Vec2::new(5, 8))
+        .place(PieceType::ROOK, PieceColor::WHITE, Vec2::new(1, 3))
+        .place(PieceType::ROOK, PieceColor::WHITE, Vec2::new(8, 3))
+        .config(Config::new([Vec2::new(0, 9), Vec2::new(9, 0)], vec![1, 8]))
+        .build()
+        .unwrap();
+    let piece_move = state.legal_moves_iter().find(|piece_move| piece_move.start == Vec2::new(8, 3) && piece_move.end == Vec2::new(8, 7)).unwrap();
+    let state_after = state.make_move(piece_move);
+    assert!(state_after.get_piece_at(Vec2::new(1, 3)).is_some());
+    assert_eq!(state_after.get_piece_at(Vec2::new(8, 7)).unwrap().get_id(), 3);
+}
+
+#[test]
+fn test_checkers() {
+    let state = State::default();
+    assert!(state.checkers(PieceColor::WHITE).is_empty());
+
+    let state = State::from_fen("4k3/8/8/8/8/3n4/8/r3K3 w - - 0 1".to_owned()).unwrap();
+    let mut checkers = state.checkers(PieceColor::WHITE);
+    checkers.sort_by_key(|square| (square.x, square.y));
+    assert_eq!(checkers, vec![Vec2::new(1, 1), Vec2::new(4, 3)]);
+    assert!(state.is_double_check(PieceColor::WHITE));
+    assert!(state.get_legal_moves().iter().all(|piece_move| piece_move.piece.get_piece_type() == PieceType::KING));
+
+    let state = State::from_fen("4k3/8/8/8/8/8/8/r3K3 w - - 0 1".to_owned()).unwrap();
+    assert_eq!(state.checkers(PieceColor::WHITE), vec![Vec2::new(1, 1)]);
+    assert!(!state.is_double_check(PieceColor::WHITE));
+}
+
+#[test]
+fn test_see() {
+    let knight = Vec2::new(5, 5);
+    let state = State::from_fen("4k3/8/3p4/4n3/3P4/8/8/4K3 w - - 0 1".to_owned()).unwrap();
+    assert_eq!(state.attackers(knight, PieceColor::WHITE), vec![Vec2::new(4, 4)]);
+    assert_eq!(state.attackers(knight, PieceColor::BLACK), vec![Vec2::new(4, 6)]);
+    assert_eq!(state.see(knight, PieceColor::WHITE), 220);
+
+    let state = State::from_fen("4k3/8/3p4/4n3/2N5/8/8/4K3 w - - 0 1".to_owned()).unwrap();
+    assert_eq!(state.see(knight, PieceColor::WHITE), 0);
+
+    let state = State::from_fen("7k/8/3p4/4n3/8/8/4R3/K7 w - - 0 1".to_owned()).unwrap();
+    assert_eq!(state.see(knight, PieceColor::WHITE), -180);
+
+    let state = State::from_fen("7k/8/8/4n3/8/8/4R3/K7 w - - 0 1".to_owned()).unwrap();
+    assert_eq!(state.see(knight, PieceColor::WHITE), 320);
+    assert_eq!(state.see(Vec2::new(5, 4), PieceColor::WHITE), 0);
+
+    // the rook behind joins in once the first one has captured
+    let state = State::from_fen("4r2k/8/8/4n3/8/8/4R3/K3R3 w - - 0 1".to_owned()).unwrap();
+    assert_eq!(state.see(knight, PieceColor::WHITE), 320);
+}
+
+#[test]
+fn test_game_result() {
+    let state = State::default();
+    assert_eq!(state.result(), GameResult::Ongoing);
+    assert!(!state.is_game_over());
+
+    let resigned = state.clone().resign(PieceColor::WHITE);
+    assert_eq!(resigned.result(), GameResult::BlackWins);
+    assert!(resigned.is_game_over());
+    assert_eq!(state.clone().with_result(GameResult::Draw).result(), GameResult::Draw);
+
+    let mated = State::from_fen("R5k1/5ppp/8/8/8/8/8/6K1 b - - 0 1".to_owned()).unwrap();
+    assert_eq!(mated.result(), GameResult::WhiteWins);
+    assert!(!mated.is_stalemate());
+
+    let stalemate = State::from_fen("k7/8/1Q6/8/8/8/8/7K b - - 0 1".to_owned()).unwrap();
+    assert!(stalemate.is_stalemate());
+    assert_eq!(stalemate.result(), GameResult::Draw);
+    // a declared result overrides the board
+    assert_eq!(stalemate.clone().resign(PieceColor::WHITE).result(), GameResult::BlackWins);
+    // declaring it ongoing hands the result back to the board
+    let resumed = stalemate.resign(PieceColor::WHITE).with_result(GameResult::Ongoing);
+    assert_eq!(resumed.result(), GameResult::Draw);
+}
+
+#[test]
+fn test_legal_move_count_and_mobility() {
+    for fen in [START_FEN, "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1", "r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 0 0"] {
+        let state = State::from_fen(fen.to_owned()).unwrap();
+        assert_eq!(state.legal_move_count(), state.get_legal_moves().len());
+        let mobility: usize = state.get_pieces().iter()
+            .filter(|piece| piece.is_alive() && (piece.get_color() == state.to_move))
+            .map(|piece| state.mobility(piece))
+            .sum();
+        assert_eq!(mobility, state.legal_move_count());
+    }
+
+    // the side not to move is measured too
+    let state = State::default();
+    let knight = state.get_piece_at(Vec2::new(2, 8)).unwrap();
+    assert_eq!(state.mobility(knight), 2);
+}
+
+#[test]
+fn test_legal_moves_from() {
+    let state = State::from_fen("4k3/8/8/8/8/8/8/N3K3 w - - 0 1".to_owned()).unwrap();
+    let mut ends: Vec<Vec2> = state.legal_moves_from(Vec2::new(1, 1)).iter().map(|piece_move| piece_move.end).collect();
+    ends.sort_by_key(|end| (end.x, end.y));
+    assert_eq!(ends, vec![Vec2::new(2, 3), Vec2::new(3, 2)]);
+
+    // empty square and the opponent's king
+    assert!(state.legal_moves_from(Vec2::new(4, 4)).is_empty());
+    assert!(state.legal_moves_from(Vec2::new(5, 8)).is_empty());
+}
+
+#[test]
+fn test_from_fen_sized() {
+    // gardner minichess on a 5x5 board
+    let state = State::from_fen_sized("rnbqk/ppppp/5/PPPPP/RNBQK w - - 0 1".to_owned(), 5, 5).unwrap();
+    assert_eq!(state.legal_move_count(), 7);
+    assert_eq!(state.config.promotion_lines, vec![1, 5]);
+    assert_eq!(state.to_fen(), "rnbqk/ppppp/5/PPPPP/RNBQK w - - 0 1");
+
+    // runs of empty squares can take more than one digit
+    let fen = "k9/10/10/10/10/10/10/10/10/9K w - - 0 1";
+    let state = State::from_fen_sized(fen.to_owned(), 10, 10).unwrap();
+    assert_eq!(state.get_piece_at(Vec2::new(10, 1)).unwrap().get_piece_type(), PieceType::KING);
+    assert_eq!(state.to_fen(), fen);
+
+    let error = State::from_fen_sized("rnbqk/ppppp/5/PPPPP w - - 0 1".to_owned(), 5, 5).unwrap_err();
+    assert!(error.to_string().contains("Expected 5 ranks"), "{}", error);
+    let error = State::from_fen_sized("rnbqk/pppp/5/5/RNBQK w - - 0 1".to_owned(), 5, 5).unwrap_err();
+    assert_eq!(error, ParseError::BadRankWidth { rank: "pppp".to_owned(), found: 4, expected: 5 });
+    assert!(State::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP w KQkq - 0 1".to_owned()).is_err());
+    assert!(State::from_fen("rnbqkbnr/ppppzppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1".to_owned()).is_err());
+}
+
+#[test]
+fn test_validate_fen() {
+    assert_eq!(State::validate_fen(START_FEN, false), Ok(()));
+    assert_eq!(State::validate_fen("4k3/8/8/8/8/8/8/4K3 w - -", false), Ok(()));
+
+    let errors = State::validate_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPP/RNBQKBNRK x KQkq e4 0 1", false).unwrap_err();
+    assert_eq!(errors.len(), 5, "{:?}", errors);
+    let messages: Vec<String> = errors.iter().map(ParseError::to_string).collect();
+    assert!(messages.contains(&"Rank 'PPPPPPP' covers 7 files instead of 8.".to_owned()));
+    assert!(messages.contains(&"Rank 'RNBQKBNRK' covers 9 files instead of 8.".to_owned()));
+    assert!(messages.contains(&"Expected one WHITE king but found 2.".to_owned()));
+    assert!(errors.contains(&ParseError::InvalidColor("x".to_owned())));
+    assert!(errors.contains(&ParseError::EnPassantRank { square: "e4".to_owned(), rank: 6 }));
+
+    assert_eq!(State::validate_fen("4k3/8/8/8/8/8/8/4K3", false).unwrap_err().len(), 1);
+    assert_eq!(State::validate_fen("8/8/8/8/8/8/8/8 b - e3 0 1", false).unwrap_err().len(), 3);
+    let error = State::from_fen("8/8/8/8/8/8/8/4K3 w - - 0 1".to_owned()).unwrap_err();
+    assert_eq!(error, ParseError::KingCount { color: PieceColor::BLACK, found: 0 });
+    assert_eq!(error.to_string(), "Expected one BLACK king but found 0.");
+    let error = State::from_fen("8/8/8/8/8/8/8/8 w - - 0 1".to_owned()).unwrap_err();
+    assert_eq!(error.to_string(), "Expected one WHITE king but found 0. Expected one BLACK king but found 0.");
+}
+
+#[test]
+fn test_strict_fen_rejects_pawns_on_back_ranks() {
+    let fen = "3Pk3/8/8/8/8/8/8/p3K3 w - - 0 1";
+    assert!(State::from_fen(fen.to_owned()).is_ok());
+    assert_eq!(State::validate_fen(fen, false), Ok(()));
+    assert_eq!(State::validate_fen(fen, true).unwrap_err(), vec![
+        ParseError::PawnOnBackRank(Vec2::new(4, 8)),
+        ParseError::PawnOnBackRank(Vec2::new(1, 1)),
+    ]);
+    let error = State::from_fen_strict(fen.to_owned()).unwrap_err();
+    assert_eq!(error.to_string(), "Pawn on d8 stands on the first or last rank. Pawn on a1 stands on the first or last rank.");
+    assert!(State::from_fen_strict(START_FEN.to_owned()).is_ok());
+    assert!(State::validate_fen_sized("4k/5/5/5/P3K w - - 0 1", 5, 5, true).is_err());
+    assert!(State::validate_fen_sized("4k/5/5/5/P3K w - - 0 1", 5, 5, false).is_ok());
+}
+
+#[test]
+fn test_from_ascii() {
+    let board = "
+        ....k...
+        ...r....
+        ........
+        ........
+        ........
+        ........
+        ....P...
+        ....K...
+    ";
+    let state = State::from_ascii(board, PieceColor::BLACK).unwrap();
+    assert_eq!(state.to_fen(), "4k3/3r4/8/8/8/8/4P3/4K3 b - - 0 1");
+    assert_eq!(State::from_ascii(&state.to_string(), PieceColor::BLACK).unwrap(), state);
+
+    let state = State::default();
+    assert_eq!(State::from_ascii(&state.to_string(), PieceColor::WHITE).unwrap().to_string(), state.to_string());
+
+    assert!(State::from_ascii("k..\n...\n..K", PieceColor::WHITE).is_ok());
+    assert!(State::from_ascii("k..\n.1.\n..K", PieceColor::WHITE).is_err());
+    assert!(State::from_ascii("k..\n....\n..K", PieceColor::WHITE).is_err());
+}
+
+#[test]
+fn test_from_epd() {
+    let epd = "2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - bm Qg6; id \"WAC.001; with a semicolon\";";
+    let (state, operations) = State::from_epd(epd.to_owned()).unwrap();
+    assert_eq!(state.to_fen(), "2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - 0 1");
+    assert_eq!(operations.get("bm").unwrap(), "Qg6");
+    assert_eq!(operations.get("id").unwrap(), "WAC.001; with a semicolon");
+
+    let (state, operations) = State::from_epd("4k3/8/8/8/8/8/8/4K3 b - - hmvc 12; fmvn 40; am Kd1 Ke2".to_owned()).unwrap();
+    assert_eq!((state.half_moves, state.full_moves), (12, 40));
+    assert_eq!(state.to_move, PieceColor::BLACK);
+    assert_eq!(operations.get("am").unwrap(), "Kd1 Ke2");
+
+    assert!(State::from_epd("4k3/8/8/8/8/8/8/4K3 w -".to_owned()).is_err());
+    assert!(State::from_epd("4k3/8/8/8/8/8/8/4K3 w - - id \"open;".to_owned()).is_err());
+    assert_eq!(State::from_epd("4k3/8/8/8/8/8/8/4K3 w - - bm Ke2; bm Kd2;".to_owned()).unwrap_err(), ParseError::DuplicateOpcode("bm".to_owned()));
+}
+
+#[test]
+fn test_move_clocks() {
+    let play = |state: State, from: (i32, i32), to: (i32, i32)| {
+        let piece_move = state.legal_moves_from(Vec2::new(from.0, from.1)).into_iter()
+            .find(|piece_move| piece_move.end == Vec2::new(to.0, to.1))
+            .unwrap();
+        state.make_move(piece_move)
+    };
+    let state = State::from_fen("r3k3/8/8/8/8/8/4P3/R3K1N1 w Qq - 7 10".to_owned()).unwrap();
+
+    let state = play(state, (7, 1), (6, 3));
+    assert_eq!((state.half_moves, state.full_moves), (8, 10));
+    let state = play(state, (5, 8), (4, 8));
+    assert_eq!((state.half_moves, state.full_moves), (9, 11));
+    let state = play(state, (5, 2), (5, 4));
+    assert_eq!((state.half_moves, state.full_moves), (0, 11));
+    let state = play(state, (1, 8), (1, 1));
+    assert_eq!((state.half_moves, state.full_moves), (0, 12));
+
+    // castling moves no pawn and captures nothing even though it targets a rook
+    let state = State::from_fen("4k3/8/8/8/8/8/8/R3K3 w Q - 3 1".to_owned()).unwrap();
+    let state = play(state, (5, 1), (1, 1));
+    assert_eq!(state.half_moves, 4);
+}
+
+#[test]
+fn test_repetition_count() {
+    let mut state = State::default();
+    assert_eq!(state.repetition_count(), 1);
+    for uci in ["g1f3", "g8f6", "f3g1", "f6g8", "g1f3", "g8f6", "f3g1", "f6g8"] {
+        state = state.apply_uci(uci).unwrap();
+    }
+    assert_eq!(state.repetition_count(), 3);
+    assert!(state.is_threefold_repetition());
+    assert_eq!(state.result(), GameResult::Draw);
+
+    // a pawn move starts the count over and taking it back brings the earlier positions back
+    state = state.apply_uci("e2e4").unwrap();
+    assert_eq!(state.repetition_count(), 1);
+    assert!(!state.is_threefold_repetition());
+    assert_eq!(state.result(), GameResult::Ongoing);
+    state.unmake_move().unwrap();
+    assert_eq!(state.repetition_count(), 3);
+    state.unmake_move().unwrap();
+    assert_eq!(state.repetition_count(), 2);
+}
+
+#[test]
+fn test_side_to_move_helpers() {
+    let state = State::default();
+    assert_eq!(state.us(), PieceColor::WHITE);
+    assert_eq!(state.them(), PieceColor::BLACK);
+    assert!(state.side_to_move_is_white());
+
+    let state = State::from_fen("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 2".to_owned()).unwrap();
+    assert_eq!(state.clone().with_side_to_move(PieceColor::WHITE), state);
+    let flipped = state.with_side_to_move(PieceColor::BLACK);
+    assert_eq!(flipped.us(), PieceColor::BLACK);
+    assert!(!flipped.side_to_move_is_white());
+    assert_eq!(flipped.get_en_passant_target(), None);
+    assert_eq!(flipped.to_fen(), "4k3/8/8/3pP3/8/8/8/4K3 b - - 0 2");
+}
+
+#[test]
+fn test_promotion_must_be_allowed() {
+    let state = State::from_fen("4k3/P7/8/8/8/8/8/4K3 w - - 0 1".to_owned()).unwrap();
+    let (from, to) = (Vec2::new(1, 7), Vec2::new(1, 8));
+    assert!(state.is_move_legal(from, to, Some(PieceType::QUEEN)));
+    assert!(!state.is_move_legal(from, to, Some(PieceType::KING)));
+    assert!(!state.is_move_legal(from, to, Some(PieceType::PAWN)));
+    assert!(!state.is_move_legal(from, to, Some(PieceType::NULL)));
+
+    let pawn = state.get_piece_at(from).unwrap().clone();
+    let promote = |piece_type| Move::new(from, to, pawn.clone(), None, Some(piece_type), false, None, false);
+    let queened = state.clone().try_make_move(promote(PieceType::QUEEN)).unwrap();
+    assert_eq!(queened.get_piece_at(to).unwrap().get_piece_type(), PieceType::QUEEN);
+    assert_eq!(state.clone().try_make_move(promote(PieceType::KING)).unwrap_err(), "Cannot promote to king.");
+    assert!(state.clone().try_make_move(promote(PieceType::PAWN)).is_err());
+
+    // a king among the options is still refused
+    let mut state = state;
+    state.config.promotion_options.push(PieceType::KING);
+    assert!(!state.config.allows_promotion(PieceType::KING));
+    assert!(!state.is_move_legal(from, to, Some(PieceType::KING)));
+    assert!(state.try_make_move(promote(PieceType::KING)).is_err());
+}
+
+#[test]
+fn test_try_make_move_checks_the_move() {
+    let state = State::default();
+    let e4 = state.legal_moves_from(Vec2::new(5, 2)).into_iter().find(|piece_move| piece_move.end == Vec2::new(5, 4)).unwrap();
+    let after = state.clone().try_make_move(e4.clone()).unwrap();
+
+    // white moved already, and the pawn is no longer on e2
+    assert!(after.clone().try_make_move(e4.clone()).is_err());
+    let black_pawn = state.get_piece_at(Vec2::new(5, 7)).unwrap().clone();
+    let wrong_side = Move::new(Vec2::new(5, 7), Vec2::new(5, 5), black_pawn, None, None, false, None, false);
+    assert_eq!(state.clone().try_make_move(wrong_side.clone()).unwrap_err(), "It is WHITE's turn, not BLACK's.");
+    let stale = after.clone().with_side_to_move(PieceColor::WHITE).try_make_move(e4.clone());
+    assert!(stale.is_err());
+    let misplaced = Move { start: Vec2::new(5, 3), ..e4.clone() };
+    assert!(state.clone().try_make_move(misplaced).is_err());
+
+    // the unchecked path plays whatever it is given
+    assert!(state.make_move_unchecked(wrong_side).is_ok());
+}
+
+#[test]
+fn test_compact_drops_captured_pieces() {
+    let moves = ["e2e4", "d7d5", "e4d5", "d8d5", "b1c3", "d5a5", "d1h5", "a5c3"];
+    let mut kept = State::default();
+    let mut compacted = State::default().with_capture_mode(CaptureMode::Compact);
+    for uci in moves {
+        kept = kept.apply_uci(uci).unwrap();
+        compacted = compacted.apply_uci(uci).unwrap();
+    }
+    assert_eq!(kept.get_pieces().len(), 32);
+    assert_eq!(compacted.get_pieces().len(), 29);
+    assert_eq!(compacted, kept);
+    assert_eq!(compacted.get_legal_moves(), kept.get_legal_moves());
+
+    // the kings are still found once the list has shrunk
+    assert_eq!(compacted.get_royal_pieces(PieceColor::WHITE)[0].get_position(), &Vec2::new(5, 1));
+    assert_eq!(compacted.find(PieceType::KING, PieceColor::BLACK)[0].get_position(), &Vec2::new(5, 8));
+    assert!(!compacted.is_king_in_check(PieceColor::WHITE));
+    assert_eq!(compacted.get_piece_at(Vec2::new(3, 3)).unwrap().get_piece_type(), PieceType::QUEEN);
+
+    // a capture cannot be taken back once its piece is gone, in the default mode it can
+    let before = compacted.clone();
+    assert!(compacted.unmake_move().is_err());
+    assert_eq!(compacted.history(), before.history());
+    assert_eq!(compacted, before);
+    assert!(kept.clone().unmake_move().is_ok());
+    kept.compact();
+    assert_eq!(kept.get_pieces().len(), 29);
+    assert_eq!(kept.get_capture_mode(), CaptureMode::KeepForUndo);
+}
+
+#[test]
+fn test_translate() {
+    let kiwipete = State::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1".to_owned()).unwrap();
+    let delta = Vec2::new(-40, 1000);
+    let moved = kiwipete.translate(delta).unwrap();
+    assert_eq!(moved.legal_move_count(), kiwipete.legal_move_count());
+    assert_eq!(moved.get_piece_at(Vec2::new(5, 1) + delta).unwrap().get_piece_type(), PieceType::KING);
+    assert_eq!(moved.bounding_box(), (Vec2::new(1, 1) + delta, Vec2::new(8, 8) + delta));
+    assert_eq!(moved.config.promotion_lines, vec![1001, 1008]);
+    assert_eq!(moved.translate(-delta).unwrap(), kiwipete);
+
+    // the en passant square moves along
+    let state = State::from_fen("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 2".to_owned()).unwrap();
+    let moved = state.translate(Vec2::new(3, -2)).unwrap();
+    assert_eq!(moved.get_en_passant_target(), Some(Vec2::new(7, 4)));
+    assert_eq!(moved.legal_move_count(), state.legal_move_count());
+
+    // on a torus the pieces wrap around the same board
+    let torus = StateBuilder::new()
+        .config(Config::toroidal(8, 8, vec![]))
+        .place(PieceType::ROOK, PieceColor::WHITE, Vec2::new(1, 4))
+        .place(PieceType::KING, PieceColor::WHITE, Vec2::new(3, 7))
+        .place(PieceType::KING, PieceColor::BLACK, Vec2::new(6, 2))
+        .build()
+        .unwrap();
+    let moved = torus.translate(Vec2::new(-3, 13)).unwrap();
+    assert_eq!(moved.get_piece_at(Vec2::new(6, 1)).unwrap().get_piece_type(), PieceType::ROOK);
+    assert_eq!(moved.legal_move_count(), torus.legal_move_count());
+
+    let far = StateBuilder::new()
+        .place(PieceType::KING, PieceColor::WHITE, Vec2::new(i32::MAX - 1, 0))
+        .place(PieceType::KING, PieceColor::BLACK, Vec2::ZERO)
+        .build()
+        .unwrap();
+    assert!(far.translate(Vec2::new(2, 0)).is_err());
+    assert!(far.translate(Vec2::new(-5, 7)).unwrap().get_piece_at(Vec2::new(i32::MAX - 6, 7)).is_some());
+}
+
+#[test]
+fn test_to_fen_on_infinite_board() {
+    let state = StateBuilder::new()
+        .place(PieceType::KING, PieceColor::WHITE, Vec2::new(-3, 0))
+        .place(PieceType::KING, PieceColor::BLACK, Vec2::new(2, 1))
+        .build()
+        .unwrap();
+    assert_eq!(state.to_fen(), "5k/K5 w - - 0 0");
+}
+
+#[test]
+fn test_missing_fullmove_number_is_one() {
+    let state = State::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0".to_owned()).unwrap();
+    assert_eq!(state.full_moves, 1);
+    let state = State::from_fen("4k3/8/8/8/8/8/8/4K3 b - -".to_owned()).unwrap();
+    assert_eq!(state.to_fen(), "4k3/8/8/8/8/8/8/4K3 b - - 0 1");
+}
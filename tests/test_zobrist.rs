@@ -0,0 +1,71 @@
+use quasar::state::*;
+use quasar::moves::Move;
+use quasar::pieces::*;
+use quasar::zobrist::ZobristTable;
+use glam::IVec2 as Vec2;
+
+const START_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 0";
+
+#[test]
+fn test_table_is_deterministic() {
+    let first = ZobristTable::default();
+    let second = ZobristTable::default();
+    let square = Vec2::new(1_000_000, -42);
+    assert_eq!(
+        first.piece_key(PieceType::QUEEN, PieceColor::BLACK, square),
+        second.piece_key(PieceType::QUEEN, PieceColor::BLACK, square)
+    );
+    assert_ne!(
+        first.piece_key(PieceType::QUEEN, PieceColor::BLACK, square),
+        first.piece_key(PieceType::QUEEN, PieceColor::WHITE, square)
+    );
+}
+
+#[test]
+fn test_move_and_inverse_restore_hash() {
+    let state = State::from_fen(START_FEN.to_owned()).unwrap();
+    let original = state.hash();
+
+    let knight = state.get_piece_at(Vec2::new(7, 1)).unwrap().clone();
+    let forward = Move::new(Vec2::new(7, 1), Vec2::new(6, 3), knight, None, None, false, None, false);
+    let state = state.make_move(forward);
+    assert_ne!(state.hash(), original);
+
+    let knight = state.get_piece_at(Vec2::new(6, 3)).unwrap().clone();
+    let back = Move::new(Vec2::new(6, 3), Vec2::new(7, 1), knight, None, None, false, None, false);
+    let state = state.make_move(back);
+    assert_eq!(state.hash(), original);
+}
+
+#[test]
+fn test_side_to_move_changes_hash() {
+    let white = State::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 0".to_owned()).unwrap();
+    let black = State::from_fen("4k3/8/8/8/8/8/8/4K3 b - - 0 0".to_owned()).unwrap();
+    assert_ne!(white.hash(), black.hash());
+}
+
+#[test]
+fn test_castling_rights_change_hash() {
+    let all = State::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1".to_owned()).unwrap();
+    let kingside = State::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w Kkq - 0 1".to_owned()).unwrap();
+    let none = State::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w - - 0 1".to_owned()).unwrap();
+    assert_eq!(all.castling_rights().len(), 4);
+    assert_eq!(kingside.castling_rights().len(), 3);
+    assert_ne!(all.hash(), kingside.hash());
+    assert_ne!(kingside.hash(), none.hash());
+    assert_ne!(all, kingside);
+    assert_eq!(none, State::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w - - 5 9".to_owned()).unwrap());
+
+    // the rooks come back but the rights do not, the count starts over from there
+    let mut state = all.clone();
+    for uci in ["a1b1", "a8b8", "b1a1", "b8a8"] {
+        state = state.apply_uci(uci).unwrap();
+    }
+    assert_ne!(state, all);
+    assert_eq!(state.castling_rights().len(), 2);
+    assert_eq!(state.repetition_count(), 1);
+    for uci in ["a1b1", "a8b8", "b1a1", "b8a8"] {
+        state = state.apply_uci(uci).unwrap();
+    }
+    assert_eq!(state.repetition_count(), 2);
+}